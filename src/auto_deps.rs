@@ -0,0 +1,77 @@
+//! Heuristic detection of the dependencies of a source file from its imports.
+//! Only available with the `auto-deps` feature.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Languages whose imports can be followed
+#[derive(Clone, Copy)]
+pub enum Language {
+    /// Local includes of C and C++, `#include "file.h"`
+    C,
+}
+
+impl Language {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "c" => Ok(Language::C),
+            _ => Err(format!(
+                "Unsupported language for --auto-deps: {value} (expected c)"
+            )),
+        }
+    }
+
+    /// The imported paths of one source file, as written
+    fn imports(self, source: &str) -> Vec<String> {
+        match self {
+            Language::C => source
+                .lines()
+                .filter_map(|line| {
+                    // # include "file.h", system includes in <> are not followed
+                    let directive = line.trim_start().strip_prefix('#')?.trim_start();
+                    let path = directive.strip_prefix("include")?.trim_start();
+                    let path = path.strip_prefix('"')?;
+                    let (path, _) = path.split_once('"')?;
+                    Some(path.to_string())
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Follows the imports of the file (relative to `base_directory`) transitively and returns the
+/// imported files relative to `base_directory`, or absolute if outside of it. Imports are resolved
+/// relative to the importing file, imports that don't exist there (e.g. found via include paths)
+/// are skipped.
+pub fn dependencies(language: Language, base_directory: &Path, filename: &str) -> Vec<String> {
+    let base_directory = base_directory
+        .canonicalize()
+        .unwrap_or_else(|_| base_directory.to_path_buf());
+    let Ok(start) = base_directory.join(filename).canonicalize() else {
+        return Vec::new();
+    };
+
+    let mut visited: HashSet<PathBuf> = HashSet::from([start.clone()]);
+    let mut pending = vec![start];
+    let mut dependencies = Vec::new();
+    while let Some(file) = pending.pop() {
+        let Ok(source) = fs::read_to_string(&file) else {
+            continue; // binary or unreadable files have no imports
+        };
+        let directory = file.parent().unwrap_or(&base_directory).to_path_buf();
+        for import in language.imports(&source) {
+            let Ok(imported) = directory.join(&import).canonicalize() else {
+                continue;
+            };
+            if imported.is_file() && visited.insert(imported.clone()) {
+                let relative = imported.strip_prefix(&base_directory).unwrap_or(&imported);
+                dependencies.push(relative.to_string_lossy().into_owned());
+                pending.push(imported);
+            }
+        }
+    }
+    dependencies
+}
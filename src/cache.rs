@@ -0,0 +1,168 @@
+use crate::{
+    manifest::ResolvedFiles,
+    vcs::{CommitInfo, VersionControl},
+};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// One monitored entry together with its cached result
+struct CachedEntry {
+    key: String,
+    resolved: ResolvedFiles,
+    /// The files the pathspecs of `resolved` matched when the entry was last queried
+    matched: HashSet<PathBuf>,
+    commit: Option<CommitInfo>,
+}
+
+/// Caches the latest commit of several entries, for long-running processes that are notified about
+/// changed files and only want to re-query the entries affected by them.
+///
+/// The cache keeps an index of the files each entry's pathspecs match (resolved via
+/// [`VersionControl::list_files`]), so wildcards, excludes and directories are handled like
+/// git handles them. The index of an entry is refreshed whenever the entry is re-queried.
+/// A new file that matches the pathspecs of an entry but was not yet known to the index is not
+/// detected by [`Cache::update`], use [`Cache::refresh`] for such structural changes.
+pub struct Cache<V: VersionControl> {
+    repository: V,
+    base_directory: PathBuf,
+    entries: Vec<CachedEntry>,
+}
+
+impl<V: VersionControl> Cache<V> {
+    /// Queries all entries once. Relative paths are relative to `base_directory`, which should be
+    /// the directory the repository was opened in.
+    pub fn new(
+        repository: V,
+        base_directory: &Path,
+        entries: impl IntoIterator<Item = (String, ResolvedFiles)>,
+    ) -> Self {
+        let mut cache = Cache {
+            repository,
+            base_directory: base_directory.to_path_buf(),
+            entries: entries
+                .into_iter()
+                .map(|(key, resolved)| CachedEntry {
+                    key,
+                    resolved,
+                    matched: HashSet::new(),
+                    commit: None,
+                })
+                .collect(),
+        };
+        cache.refresh();
+        cache
+    }
+
+    /// The latest commit of an entry, None for unknown entries or entries without any commit
+    pub fn get(&self, key: &str) -> Option<&CommitInfo> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .and_then(|entry| entry.commit.as_ref())
+    }
+
+    /// Re-queries all entries
+    pub fn refresh(&mut self) {
+        for index in 0..self.entries.len() {
+            self.query(index);
+        }
+    }
+
+    /// Re-queries only the entries whose monitored files include any of the changed paths and
+    /// returns the keys of these entries. Changed paths may be absolute or relative to the base directory.
+    pub fn update(&mut self, changed_paths: &[PathBuf]) -> Vec<String> {
+        let changed: Vec<PathBuf> = changed_paths
+            .iter()
+            .map(|path| self.base_directory.join(path))
+            .collect();
+
+        let mut updated = Vec::new();
+        for index in 0..self.entries.len() {
+            let entry = &self.entries[index];
+            if changed.iter().any(|path| entry.matched.contains(path)) {
+                updated.push(entry.key.clone());
+                self.query(index);
+            }
+        }
+        updated
+    }
+
+    fn query(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        entry.commit = self
+            .repository
+            .latest_commit(&entry.resolved.history_files());
+        entry.matched = self
+            .repository
+            .list_files(&entry.resolved.files)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::mock::{commit, MockRepository};
+
+    fn resolved(files: &[&str]) -> ResolvedFiles {
+        ResolvedFiles {
+            files: files.iter().map(|file| file.to_string()).collect(),
+            aliases: Vec::new(),
+            from_fallback: false,
+            dirty_check: true,
+        }
+    }
+
+    fn cache(repository: MockRepository) -> Cache<MockRepository> {
+        let root = repository.root.clone();
+        Cache::new(
+            repository,
+            &root,
+            [
+                ("a".to_string(), resolved(&["a.typ", "shared.typ"])),
+                ("b".to_string(), resolved(&["docs"])),
+            ],
+        )
+    }
+
+    #[test]
+    fn new_queries_all_entries() {
+        let cache = cache(MockRepository::new(&[
+            (2, &["docs/b.typ"]),
+            (1, &["a.typ"]),
+        ]));
+        assert_eq!(cache.get("a").map(|commit| commit.timestamp), Some(1));
+        assert_eq!(cache.get("b").map(|commit| commit.timestamp), Some(2));
+        assert!(cache.get("unknown").is_none());
+    }
+
+    #[test]
+    fn update_only_requeries_affected_entries() {
+        let mut cache = cache(MockRepository::new(&[
+            (2, &["docs/b.typ", "shared.typ"]),
+            (1, &["a.typ"]),
+        ]));
+        // A new commit, which the cache only sees for the entries it re-queries
+        cache.repository.commits.insert(
+            0,
+            (
+                commit(3),
+                vec!["a.typ".to_string(), "docs/b.typ".to_string()],
+            ),
+        );
+
+        let updated = cache.update(&[PathBuf::from("a.typ")]);
+        assert_eq!(updated, vec!["a".to_string()]);
+        assert_eq!(cache.get("a").map(|commit| commit.timestamp), Some(3));
+        assert_eq!(cache.get("b").map(|commit| commit.timestamp), Some(2));
+
+        // Absolute paths work as well, directories match the files inside
+        let updated = cache.update(&[PathBuf::from("/mock/docs/b.typ")]);
+        assert_eq!(updated, vec!["b".to_string()]);
+        assert_eq!(cache.get("b").map(|commit| commit.timestamp), Some(3));
+    }
+}
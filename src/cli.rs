@@ -0,0 +1,766 @@
+use crate::output::{is_shell_name, Format, Template};
+#[cfg(feature = "auto-deps")]
+use change_monitor::auto_deps::Language;
+use change_monitor::{
+    config::Config,
+    git::{Repository, StatusFormat},
+    manifest::ManifestFormat,
+    vcs::{normalize_path, Identity},
+};
+use std::path::Path;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub const USAGE: &str = "<filename>... [options]
+Options:
+  --date                          output only the date of the latest commit instead of the hash
+  --tree-hash                     output the git tree hash of the monitored files instead of the commit hash
+  --fingerprint                   output a hash of the current content of the monitored files instead of the commit hash
+  --staged                        with --fingerprint, hash the staged content instead of the working tree
+  --include-submodule-pointers    with --fingerprint, include the commits of submodules recorded in the index
+  --include-ignored               with --fingerprint, include ignored files matching the monitored files
+  --age-seconds                   output the seconds since the latest commit instead of the hash
+  --age-format <unit>             with --age-seconds, output the age in whole seconds, minutes, hours, days or weeks
+  --merge-base <ref>              output the merge base of the latest commit and the ref instead of the hash
+  --breakdown                     also report the latest commits of the file alone and of its dependencies alone
+  --parent                        output the first parent of the latest commit, the state before the change
+  --number-format <%0Nd>          zero-pad numeric output to N digits, e.g. %06d
+  --hash-width <n>                abbreviate all output hashes to exactly n hex characters
+  --respect-source-date-epoch     use $SOURCE_DATE_EPOCH (if set) for the date output
+  --identity <person>             report the date, name and email of the author, committer (default) or author-or-committer
+  --empty-value <string>          print this value instead of failing if no commit affects a file
+  --clean-marker <string>         append this marker to the hash if there are no uncommitted changes
+  --deps-format <toml|json|yaml>  use this dependencies file if there are several (default: toml, json, yaml)
+  --require-manifest              fail if there is no .deps.toml next to a file
+  --auto-deps <c>                 if there is no .deps.toml entry, follow the imports of the file (feature auto-deps)
+  --max-depth <n>                 without a .deps.toml entry, only monitor files up to n directories deep
+  --files-command <command>       monitor the paths printed by the shell command instead of the .deps.toml entry
+  --by-content                    find the latest commit that introduced the current content under any path
+  --ref <revision>                search the latest commit in the history of the revision instead of HEAD
+  --diff-filter <ACDMRTUXB>       only consider commits changing the files in these ways (lowercase to exclude)
+  --commit-deps <all|file>        files used to find the latest commit (default: all)
+  --dirty-deps <all|file>         files used for the dirty check (default: all)
+  --no-dirty-check                skip the dirty check, no DIRTY marker is reported
+  --verify-clean-after            query the commit and dirty state again at the end and fail if they changed
+  --staged-only                   only consider staged changes for the DIRTY marker
+  --status-format <v1|v2>         git status porcelain format used for the dirty check (default: v2)
+  --with-diffstat                 also report the files changed, insertions and deletions of the latest commit
+  --with-branch                   also report the checked out branch
+  --detached-branch <name>        branch reported by --with-branch for a detached HEAD (default: HEAD)
+  --commit-url-template <url>     append a link to the commit, {hash} and {short} are substituted
+  --format <format>               output format: plain, json, tsv, shell, make, stamp, properties, env or pairs (default: plain)
+  --pairs                         print 'file'='value' lines, same as --format pairs
+  --template <file>               print each result by filling the {placeholders} of the template file
+  --shell-prefix <prefix>         prefix of the variable names of the shell format
+  --properties-prefix <prefix>    prefix of the keys of the properties format (default: change.monitor)
+  --output <file>                 write the output to the file instead of stdout
+  --json-out <file>               also write the results in the json format to the file
+  --env-out <file>                also write the results in the env format to the file
+  --touch <file>                  update the modification time of the file, creating it if needed
+  --dirty-only                    only report uncommitted changes: print nothing if clean, DIRTY and the files otherwise
+  --fail-on-dirty                 with --dirty-only, exit with 1 if there are uncommitted changes
+  --print-pathspecs               print the pathspecs passed to git log, to reproduce the query
+  --dump-resolved                 print the resolved files of all entries of the dependencies file as JSON
+  --progress                      show the number of processed files on stderr, if it is a terminal
+  --provenance                    print the commit and blob hashes of every monitored file as JSON
+  --window <n>                    list the n latest commits affecting the files with their dates
+  --commit-delta                  for two files, print the number of commits from the latest commit of the first to the second
+  --compare-manifests <old> <new> print the entries added, removed and changed between two dependencies files
+  --build-index <file>            write the latest commit of every tracked file to an index file, in one walk
+  --use-index <file>              look up the latest commits in the index file instead of querying git
+  --glob                          treat the filenames as glob patterns (default: if they contain *, ? or [ and don't exist)
+  --single-repo                   fail if the files given span multiple repositories
+  --dry-run                       only describe the files that would be written, on stderr
+  --fail-on-warning, -W error     treat all warnings as errors
+  --no-isolation                  let the user's git config change settings pinned by the tool
+  --no-optional-locks             don't let git take optional locks, e.g. to refresh the index (default in CI)
+  --optional-locks                let git take optional locks, also in CI
+  --vcs <git>                     select the version control system (default: auto-detected)
+  -v, --version                   print the version";
+
+/// What is printed for the latest commit
+#[derive(Clone, Copy, PartialEq)]
+pub enum Value {
+    Hash,
+    Date,
+    TreeHash,
+    Fingerprint,
+    AgeSeconds,
+    MergeBase,
+    Parent,
+}
+
+impl Value {
+    /// Whether the value describes the committed state, so the DIRTY marker applies
+    pub fn is_hash(self) -> bool {
+        matches!(self, Value::Hash | Value::TreeHash)
+    }
+}
+
+/// Formatting of numeric output, `%d` or zero-padded `%0<width>d` like printf
+#[derive(Clone, Copy)]
+pub struct NumberFormat {
+    width: usize,
+}
+
+impl NumberFormat {
+    fn parse(value: &str) -> Result<Self, String> {
+        let invalid =
+            || format!("Invalid number format: {value} (expected %d or %0<width>d, e.g. %06d)");
+        let spec = value
+            .strip_prefix('%')
+            .and_then(|spec| spec.strip_suffix('d'))
+            .ok_or_else(invalid)?;
+        let width = match spec {
+            "" => 0,
+            _ => spec
+                .strip_prefix('0')
+                .and_then(|width| width.parse().ok())
+                .ok_or_else(invalid)?,
+        };
+        Ok(NumberFormat { width })
+    }
+
+    /// Zero-pads the number to the width, longer numbers are not cut off
+    pub fn apply(self, number: u64) -> String {
+        format!("{number:0width$}", width = self.width)
+    }
+}
+
+/// Unit of the age for `--age-format`, the age is rounded down to whole units
+#[derive(Clone, Copy)]
+pub enum AgeUnit {
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+}
+
+impl AgeUnit {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "seconds" => Ok(AgeUnit::Seconds),
+            "minutes" => Ok(AgeUnit::Minutes),
+            "hours" => Ok(AgeUnit::Hours),
+            "days" => Ok(AgeUnit::Days),
+            "weeks" => Ok(AgeUnit::Weeks),
+            _ => Err(format!(
+                "Invalid age format: {value} (expected seconds, minutes, hours, days or weeks)"
+            )),
+        }
+    }
+
+    /// The age in whole units with the unit, e.g. `5 days` or `1 hour`
+    pub fn apply(self, seconds: u64, number_format: NumberFormat) -> String {
+        let (unit, length) = match self {
+            AgeUnit::Seconds => ("second", 1),
+            AgeUnit::Minutes => ("minute", 60),
+            AgeUnit::Hours => ("hour", 60 * 60),
+            AgeUnit::Days => ("day", 24 * 60 * 60),
+            AgeUnit::Weeks => ("week", 7 * 24 * 60 * 60),
+        };
+        let count = seconds / length;
+        let plural = if count == 1 { "" } else { "s" };
+        format!("{} {unit}{plural}", number_format.apply(count))
+    }
+}
+
+/// Which of the monitored files a query uses
+#[derive(Clone, Copy)]
+pub enum DepsScope {
+    /// The file and all its dependencies
+    All,
+    /// Only the file itself
+    File,
+}
+
+impl DepsScope {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "all" => Ok(DepsScope::All),
+            "file" => Ok(DepsScope::File),
+            _ => Err(format!("Invalid value: {value} (expected all or file)")),
+        }
+    }
+}
+
+/// Options parsed from the command line
+pub struct Options {
+    pub filenames: Vec<String>,
+    pub value: Value,
+    /// The reference of `--merge-base`
+    pub merge_base: Option<String>,
+    pub staged: bool,
+    pub include_submodule_pointers: bool,
+    pub include_ignored: bool,
+    pub hash_width: Option<usize>,
+    pub breakdown: bool,
+    pub number_format: NumberFormat,
+    pub age_format: Option<AgeUnit>,
+    pub respect_source_date_epoch: bool,
+    /// Whose date is reported, the name and email are only reported if given
+    pub identity: Option<Identity>,
+    pub clean_marker: Option<String>,
+    pub empty_value: Option<String>,
+    pub require_manifest: bool,
+    /// The format of the dependencies file to use, None to pick by precedence
+    pub deps_format: Option<ManifestFormat>,
+    /// Language whose imports are followed without a manifest entry
+    #[cfg(feature = "auto-deps")]
+    pub auto_deps: Option<Language>,
+    pub max_depth: Option<usize>,
+    /// Shell command printing the monitored files
+    pub files_command: Option<String>,
+    pub by_content: bool,
+    /// Revision searched instead of HEAD
+    pub revision: Option<String>,
+    pub diff_filter: Option<String>,
+    pub commit_deps: DepsScope,
+    pub dirty_deps: DepsScope,
+    pub no_dirty_check: bool,
+    pub verify_clean_after: bool,
+    pub staged_only: bool,
+    pub status_format: StatusFormat,
+    pub commit_url_template: Option<String>,
+    pub with_diffstat: bool,
+    /// The name reported for a detached HEAD, if the branch is reported
+    pub with_branch: Option<String>,
+    pub dump_resolved: bool,
+    pub print_pathspecs: bool,
+    pub provenance: bool,
+    pub window: Option<usize>,
+    pub commit_delta: bool,
+    pub compare_manifests: Option<(String, String)>,
+    pub build_index: Option<String>,
+    /// The index of `--build-index` to answer the commit queries from, see `index::Index`
+    pub use_index: Option<String>,
+    /// Files written together after all results are computed, see `sink::Transaction`
+    pub output: Option<String>,
+    pub json_out: Option<String>,
+    pub env_out: Option<String>,
+    pub touch: Option<String>,
+    pub dirty_only: bool,
+    pub fail_on_dirty: bool,
+    pub vcs: Option<String>,
+    pub no_isolation: bool,
+    /// `--no-optional-locks`, or running in CI without `--optional-locks`
+    pub no_optional_locks: bool,
+    pub single_repo: bool,
+    pub glob: bool,
+    pub fail_on_warning: bool,
+    /// Describe side effects instead of performing them
+    pub dry_run: bool,
+    pub progress: bool,
+    pub format: Format,
+    /// Replaces the format, if given
+    pub template: Option<Template>,
+    /// Prefix of the variable names of the shell format
+    pub shell_prefix: String,
+    /// Prefix of the keys of the properties format
+    pub properties_prefix: String,
+}
+
+impl Options {
+    /// The options of the library, the rest is handled by the command line tool
+    pub fn config(&self) -> Config {
+        Config {
+            revision: self.revision.clone(),
+            diff_filter: self.diff_filter.clone(),
+            dirty_check: !self.no_dirty_check,
+            staged_only: self.staged_only,
+            identity: self.identity.unwrap_or(Identity::Committer),
+            status_format: self.status_format,
+            include_submodule_pointers: self.include_submodule_pointers,
+            include_ignored: self.include_ignored,
+            isolated: !self.no_isolation,
+            no_optional_locks: self.no_optional_locks,
+            ..Config::default()
+        }
+    }
+}
+
+/// Parses the command line arguments (without the program name).
+/// Exits directly for `--version`.
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut filenames = Vec::new();
+    let mut value = None;
+    let mut merge_base = None;
+    let mut staged = false;
+    let mut include_submodule_pointers = false;
+    let mut include_ignored = false;
+    let mut hash_width = None;
+    let mut breakdown = false;
+    let mut age_format = None;
+    let mut number_format = NumberFormat { width: 0 };
+    let mut respect_source_date_epoch = false;
+    let mut clean_marker = None;
+    let mut empty_value = None;
+    let mut identity = None;
+    let mut require_manifest = false;
+    let mut deps_format = None;
+    #[cfg(feature = "auto-deps")]
+    let mut auto_deps = None;
+    let mut max_depth = None;
+    let mut files_command = None;
+    let mut by_content = false;
+    let mut revision = None;
+    let mut diff_filter = None;
+    let mut commit_deps = DepsScope::All;
+    let mut dirty_deps = DepsScope::All;
+    let mut no_dirty_check = false;
+    let mut verify_clean_after = false;
+    let mut staged_only = false;
+    let mut status_format = StatusFormat::V2;
+    let mut commit_url_template = None;
+    let mut with_diffstat = false;
+    let mut with_branch = false;
+    let mut detached_branch = None;
+    let mut dump_resolved = false;
+    let mut print_pathspecs = false;
+    let mut provenance = false;
+    let mut window = None;
+    let mut commit_delta = false;
+    let mut compare_manifests = None;
+    let mut build_index = None;
+    let mut use_index = None;
+    let mut output = None;
+    let mut json_out = None;
+    let mut env_out = None;
+    let mut touch = None;
+    let mut dirty_only = false;
+    let mut fail_on_dirty = false;
+    let mut vcs = None;
+    let mut no_isolation = false;
+    let mut no_optional_locks = None;
+    let mut single_repo = false;
+    let mut glob = false;
+    let mut fail_on_warning = false;
+    let mut dry_run = false;
+    let mut progress = false;
+    let mut format = Format::PLAIN;
+    let mut template = None;
+    let mut shell_prefix = String::new();
+    let mut properties_prefix = "change.monitor".to_string();
+
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        // Values can be given as `--option value` or `--option=value`
+        let (flag, mut inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg, None),
+        };
+        let mut value_of = |flag: &str| {
+            inline_value
+                .take()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{flag} requires a value"))
+        };
+
+        match flag {
+            "-v" | "--version" => {
+                eprintln!("Version: {}", VERSION);
+                std::process::exit(0);
+            }
+            "--date" | "--tree-hash" | "--fingerprint" | "--age-seconds" | "--merge-base"
+            | "--parent" => {
+                let selected = match flag {
+                    "--date" => Value::Date,
+                    "--tree-hash" => Value::TreeHash,
+                    "--fingerprint" => Value::Fingerprint,
+                    "--merge-base" => {
+                        merge_base = Some(value_of(flag)?.to_string());
+                        Value::MergeBase
+                    }
+                    "--parent" => Value::Parent,
+                    _ => Value::AgeSeconds,
+                };
+                if value.is_some_and(|value| value != selected) {
+                    return Err(
+                        "--date, --tree-hash, --fingerprint, --age-seconds, --merge-base and --parent cannot be combined"
+                            .to_string(),
+                    );
+                }
+                value = Some(selected);
+            }
+            "--hash-width" => hash_width = Some(parse_positive(flag, value_of(flag)?)?),
+            "--breakdown" => breakdown = true,
+            "--age-format" => age_format = Some(AgeUnit::parse(value_of(flag)?)?),
+            "--number-format" => number_format = NumberFormat::parse(value_of(flag)?)?,
+            "--respect-source-date-epoch" => respect_source_date_epoch = true,
+            "--commit-url-template" => {
+                commit_url_template = Some(value_of(flag)?.to_string());
+            }
+            "--with-diffstat" => with_diffstat = true,
+            "--with-branch" => with_branch = true,
+            "--detached-branch" => detached_branch = Some(value_of(flag)?.to_string()),
+            "--clean-marker" => {
+                let marker = value_of(flag)?;
+                // An empty marker disables it
+                clean_marker = Some(marker.to_string()).filter(|marker| !marker.is_empty());
+            }
+            "--empty-value" => empty_value = Some(value_of(flag)?.to_string()),
+            "--identity" => identity = Some(Identity::parse(value_of(flag)?)?),
+            "--staged" => staged = true,
+            "--include-submodule-pointers" => include_submodule_pointers = true,
+            "--include-ignored" => include_ignored = true,
+            "--require-manifest" => require_manifest = true,
+            "--deps-format" => match ManifestFormat::parse(value_of(flag)?)? {
+                ManifestFormat::Toml => deps_format = Some(ManifestFormat::Toml),
+                other => {
+                    return Err(format!(
+                        "Reading {} dependencies files is not supported yet",
+                        other.name()
+                    ))
+                }
+            },
+            #[cfg(feature = "auto-deps")]
+            "--auto-deps" => auto_deps = Some(Language::parse(value_of(flag)?)?),
+            #[cfg(not(feature = "auto-deps"))]
+            "--auto-deps" => {
+                return Err(
+                    "--auto-deps requires the auto-deps feature (cargo build --features auto-deps)"
+                        .to_string(),
+                )
+            }
+            "--max-depth" => max_depth = Some(parse_positive(flag, value_of(flag)?)?),
+            "--files-command" => files_command = Some(value_of(flag)?.to_string()),
+            "--by-content" => by_content = true,
+            "--ref" => revision = Some(value_of(flag)?.to_string()),
+            "--diff-filter" => {
+                diff_filter = Some(Repository::parse_diff_filter(value_of(flag)?)?);
+            }
+            "--commit-deps" => commit_deps = DepsScope::parse(value_of(flag)?)?,
+            "--dirty-deps" => dirty_deps = DepsScope::parse(value_of(flag)?)?,
+            "--no-dirty-check" => no_dirty_check = true,
+            "--verify-clean-after" => verify_clean_after = true,
+            "--staged-only" => staged_only = true,
+            "--status-format" => {
+                status_format = StatusFormat::parse(value_of(flag)?)?;
+            }
+            "--dump-resolved" => dump_resolved = true,
+            "--print-pathspecs" => print_pathspecs = true,
+            "--provenance" => provenance = true,
+            "--window" => window = Some(parse_positive(flag, value_of(flag)?)?),
+            "--commit-delta" => commit_delta = true,
+            "--compare-manifests" => {
+                let old = value_of(flag)?.to_string();
+                let new = args
+                    .next()
+                    .ok_or_else(|| format!("{flag} requires two values"))?;
+                compare_manifests = Some((old, new.to_string()));
+            }
+            "--build-index" => build_index = Some(value_of(flag)?.to_string()),
+            "--use-index" => use_index = Some(value_of(flag)?.to_string()),
+            "--output" => output = Some(value_of(flag)?.to_string()),
+            "--json-out" => json_out = Some(value_of(flag)?.to_string()),
+            "--env-out" => env_out = Some(value_of(flag)?.to_string()),
+            "--touch" => touch = Some(value_of(flag)?.to_string()),
+            "--dirty-only" => dirty_only = true,
+            "--fail-on-dirty" => fail_on_dirty = true,
+            "--vcs" => vcs = Some(value_of(flag)?.to_string()),
+            "--no-isolation" => no_isolation = true,
+            "--no-optional-locks" => no_optional_locks = Some(true),
+            "--optional-locks" => no_optional_locks = Some(false),
+            "--format" => format = Format::parse(value_of(flag)?)?,
+            "--pairs" => format = Format::parse("pairs")?,
+            "--template" => {
+                let path = value_of(flag)?;
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read template {path}: {e}"))?;
+                // The line break at the end of the file is added by printing
+                let text = text.strip_suffix('\n').unwrap_or(&text);
+                template = Some(Template::parse(text).map_err(|e| format!("{path}: {e}"))?);
+            }
+            "--shell-prefix" => {
+                let prefix = value_of(flag)?;
+                if !is_shell_name(prefix) {
+                    return Err(format!("Invalid shell variable prefix: {prefix}"));
+                }
+                shell_prefix = prefix.to_string();
+            }
+            "--properties-prefix" => properties_prefix = value_of(flag)?.to_string(),
+            "--single-repo" => single_repo = true,
+            "--glob" => glob = true,
+            "--fail-on-warning" => fail_on_warning = true,
+            "--progress" => progress = true,
+            "--dry-run" => dry_run = true,
+            "-W" => match value_of(flag)? {
+                "error" => fail_on_warning = true,
+                other => return Err(format!("Invalid value for -W: {other} (expected error)")),
+            },
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {flag}")),
+            _ => filenames.push(arg.to_string()),
+        }
+
+        if inline_value.is_some() {
+            return Err(format!("{flag} does not take a value"));
+        }
+    }
+
+    if staged && value != Some(Value::Fingerprint) {
+        return Err("--staged requires --fingerprint".to_string());
+    }
+    if include_submodule_pointers && value != Some(Value::Fingerprint) {
+        return Err("--include-submodule-pointers requires --fingerprint".to_string());
+    }
+    if include_ignored && (value != Some(Value::Fingerprint) || staged) {
+        return Err("--include-ignored requires --fingerprint without --staged".to_string());
+    }
+
+    if age_format.is_some() && value != Some(Value::AgeSeconds) {
+        return Err("--age-format requires --age-seconds".to_string());
+    }
+
+    if breakdown && (value.is_some_and(|value| value != Value::Hash) || by_content) {
+        return Err("--breakdown cannot be combined with other values or --by-content".to_string());
+    }
+
+    if detached_branch.is_some() && !with_branch {
+        return Err("--detached-branch requires --with-branch".to_string());
+    }
+    let with_branch = with_branch.then(|| detached_branch.unwrap_or_else(|| "HEAD".to_string()));
+
+    if no_dirty_check && (dirty_only || verify_clean_after) {
+        return Err(
+            "--no-dirty-check cannot be combined with --dirty-only or --verify-clean-after"
+                .to_string(),
+        );
+    }
+
+    // The index only answers the latest commit, which these modes don't use or query differently
+    if use_index.is_some()
+        && (dirty_only
+            || verify_clean_after
+            || by_content
+            || breakdown
+            || window.is_some()
+            || commit_delta
+            || build_index.is_some())
+    {
+        return Err(
+            "--use-index cannot be combined with --dirty-only, --verify-clean-after, --by-content, --breakdown, --window, --commit-delta or --build-index"
+                .to_string(),
+        );
+    }
+
+    let writes_files =
+        output.is_some() || json_out.is_some() || env_out.is_some() || touch.is_some();
+    if writes_files
+        && (dump_resolved
+            || print_pathspecs
+            || provenance
+            || dirty_only
+            || window.is_some()
+            || commit_delta
+            || compare_manifests.is_some()
+            || build_index.is_some())
+    {
+        return Err(
+            "--output, --json-out, --env-out and --touch cannot be combined with --dump-resolved, --print-pathspecs, --provenance, --dirty-only, --window, --commit-delta, --compare-manifests or --build-index"
+                .to_string(),
+        );
+    }
+
+    // Each file is written via its own temporary file, which would be shared by two sinks
+    let sinks = [
+        ("--output", &output),
+        ("--json-out", &json_out),
+        ("--env-out", &env_out),
+        ("--touch", &touch),
+    ];
+    let sinks: Vec<(&str, &String)> = sinks
+        .into_iter()
+        .filter_map(|(flag, path)| Some((flag, path.as_ref()?)))
+        .collect();
+    for (i, (flag, path)) in sinks.iter().enumerate() {
+        if let Some((other, _)) = sinks[..i].iter().find(|(_, other)| same_file(other, path)) {
+            return Err(format!("{other} and {flag} write the same file: {path}"));
+        }
+    }
+
+    if window.is_some()
+        && (value.is_some()
+            || !matches!(format.name, "plain" | "json")
+            || template.is_some()
+            || by_content
+            || commit_delta)
+    {
+        return Err(
+            "--window only supports --format plain or json, without other values, --template, --by-content or --commit-delta"
+                .to_string(),
+        );
+    }
+
+    if commit_delta {
+        if filenames.len() != 2 {
+            return Err("--commit-delta requires exactly two filenames".to_string());
+        }
+        if value.is_some() || format.name != "plain" || template.is_some() {
+            return Err(
+                "--commit-delta cannot be combined with other values, formats or --template"
+                    .to_string(),
+            );
+        }
+    }
+
+    if compare_manifests.is_some() {
+        if !filenames.is_empty() {
+            return Err("--compare-manifests does not take filenames".to_string());
+        }
+        if !matches!(format.name, "plain" | "json") {
+            return Err("--compare-manifests only supports --format plain or json".to_string());
+        }
+    } else if build_index.is_some() {
+        if !filenames.is_empty() {
+            return Err(
+                "--build-index does not take filenames, it indexes the repository of the current directory"
+                    .to_string(),
+            );
+        }
+    } else if filenames.is_empty() {
+        return Err("No filename given".to_string());
+    }
+
+    Ok(Options {
+        filenames,
+        value: value.unwrap_or(Value::Hash),
+        merge_base,
+        staged,
+        include_submodule_pointers,
+        include_ignored,
+        hash_width,
+        breakdown,
+        number_format,
+        age_format,
+        respect_source_date_epoch,
+        clean_marker,
+        empty_value,
+        require_manifest,
+        deps_format,
+        #[cfg(feature = "auto-deps")]
+        auto_deps,
+        max_depth,
+        files_command,
+        by_content,
+        revision,
+        diff_filter,
+        commit_deps,
+        dirty_deps,
+        no_dirty_check,
+        verify_clean_after,
+        staged_only,
+        identity,
+        status_format,
+        commit_url_template,
+        with_diffstat,
+        with_branch,
+        dump_resolved,
+        print_pathspecs,
+        provenance,
+        window,
+        commit_delta,
+        compare_manifests,
+        build_index,
+        use_index,
+        output,
+        json_out,
+        env_out,
+        touch,
+        dirty_only,
+        fail_on_dirty,
+        vcs,
+        no_isolation,
+        no_optional_locks: no_optional_locks.unwrap_or_else(in_ci),
+        single_repo,
+        glob,
+        fail_on_warning,
+        dry_run,
+        progress,
+        format,
+        template,
+        shell_prefix,
+        properties_prefix,
+    })
+}
+
+/// Parses the value of a flag that takes a positive number, like `--hash-width`
+fn parse_positive(flag: &str, value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|number| *number > 0)
+        .ok_or_else(|| format!("Invalid value for {flag}: {value} (expected a positive number)"))
+}
+
+/// Whether the tool runs in CI, detected by the `CI` environment variable that GitHub Actions,
+/// GitLab CI, Travis, CircleCI and most other services set (`false` or `0` count as not set)
+fn in_ci() -> bool {
+    std::env::var("CI").is_ok_and(|value| !matches!(value.as_str(), "" | "false" | "0"))
+}
+
+/// Whether two paths of the command line name the same file, compared without resolving links
+fn same_file(a: &str, b: &str) -> bool {
+    let absolute = |path: &str| {
+        let path = Path::new(path);
+        match std::env::current_dir() {
+            Ok(cwd) => normalize_path(&cwd.join(path)),
+            Err(_) => normalize_path(path),
+        }
+    };
+    absolute(a) == absolute(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positive_numbers_are_parsed() {
+        assert_eq!(parse_positive("--window", "3"), Ok(3));
+        for value in ["0", "-1", "1.5", "", "x"] {
+            assert_eq!(
+                parse_positive("--window", value),
+                Err(format!(
+                    "Invalid value for --window: {value} (expected a positive number)"
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn number_format_pads_with_zeros() {
+        let padded = NumberFormat::parse("%06d").unwrap();
+        assert_eq!(padded.apply(1486), "001486");
+        assert_eq!(padded.apply(12345678), "12345678");
+        assert_eq!(NumberFormat::parse("%d").unwrap().apply(42), "42");
+        for value in ["%6d", "%0d", "%x", "06d", "%06"] {
+            assert!(NumberFormat::parse(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn sinks_must_write_different_files() {
+        let args = |args: &[&str]| -> Vec<String> {
+            ["change-monitor", "a.txt"]
+                .iter()
+                .chain(args)
+                .map(|arg| arg.to_string())
+                .collect()
+        };
+        let error = parse_args(&args(&[
+            "--output",
+            "out.txt",
+            "--json-out",
+            "./sub/../out.txt",
+        ]))
+        .err()
+        .unwrap();
+        assert_eq!(
+            error,
+            "--output and --json-out write the same file: ./sub/../out.txt"
+        );
+        assert!(parse_args(&args(&["--env-out", "a.env", "--touch", "a.env"])).is_err());
+        assert!(parse_args(&args(&["--output", "out.txt", "--json-out", "out.json"])).is_ok());
+    }
+}
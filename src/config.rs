@@ -0,0 +1,143 @@
+use crate::{
+    git::{Repository, StatusFormat},
+    manifest::{resolve_files, Manifest, ResolvedFiles},
+    vcs::{CommitInfo, Identity, VersionControl},
+};
+use std::path::{Path, PathBuf};
+
+/// The options of a query, so library users don't have to set up the repository and the
+/// dependencies file themselves. The default matches the command line tool without options.
+#[derive(Clone)]
+pub struct Config {
+    /// Name of the dependencies file, looked up in the directory of the monitored file
+    pub dependencies_file: String,
+    /// Revision whose history is searched for the latest commit, HEAD if None (`--ref`)
+    pub revision: Option<String>,
+    /// Only consider commits changing the files in these ways, see
+    /// [`Repository::parse_diff_filter`] (`--diff-filter`)
+    pub diff_filter: Option<String>,
+    /// Whether uncommitted changes are checked, see [`Query::dirty`] (`--no-dirty-check`)
+    pub dirty_check: bool,
+    /// Only consider staged changes for the dirty check (`--staged-only`)
+    pub staged_only: bool,
+    /// Format of the `git status` output used for the dirty check (`--status-format`)
+    pub status_format: StatusFormat,
+    /// Include the commits of submodules recorded in the index in the fingerprint
+    /// (`--include-submodule-pointers`)
+    pub include_submodule_pointers: bool,
+    /// Include ignored files in the working tree fingerprint (`--include-ignored`)
+    pub include_ignored: bool,
+    /// Pin the git config settings that change the parsed output (not `--no-isolation`)
+    pub isolated: bool,
+    /// Don't take optional locks, see [`Repository::no_optional_locks`] (`--no-optional-locks`)
+    pub no_optional_locks: bool,
+    /// Whose date, name and email are reported for the commit (`--identity`)
+    pub identity: Identity,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            dependencies_file: ".deps.toml".to_string(),
+            revision: None,
+            diff_filter: None,
+            dirty_check: true,
+            staged_only: false,
+            status_format: StatusFormat::V2,
+            include_submodule_pointers: false,
+            include_ignored: false,
+            isolated: true,
+            no_optional_locks: false,
+            identity: Identity::Committer,
+        }
+    }
+}
+
+/// The result of [`Config::query`] for one file
+pub struct Query {
+    /// The monitored files, from the entry of the dependencies file or the fallback
+    pub resolved: ResolvedFiles,
+    /// The latest commit affecting the monitored files, None if there is none
+    pub commit: Option<CommitInfo>,
+    /// Whether the monitored files have uncommitted changes, None if not checked
+    pub dirty: Option<bool>,
+}
+
+impl Config {
+    /// Opens the repository containing `cwd` with the settings of this config, which apply to the
+    /// check for the repository as well
+    pub fn open_repository(&self, cwd: &Path) -> Result<Repository, String> {
+        let mut repository = Repository::new(cwd);
+        repository.status_format = self.status_format;
+        repository.staged_only = self.staged_only;
+        repository.include_submodule_pointers = self.include_submodule_pointers;
+        repository.include_ignored = self.include_ignored;
+        repository.isolated = self.isolated;
+        repository.no_optional_locks = self.no_optional_locks;
+        repository.identity = self.identity;
+        repository.diff_filter = self.diff_filter.clone();
+        repository.revision = self.revision.clone();
+        repository.verify()
+    }
+
+    /// The path of the dependencies file for a file in `base_directory`
+    pub fn dependencies_path(&self, base_directory: &Path) -> PathBuf {
+        base_directory.join(&self.dependencies_file)
+    }
+
+    /// Determines the monitored files of a file in the base directory, see [`resolve_files`].
+    /// The dependencies file itself is an ordinary file: without an entry of its own, only its
+    /// own history is used instead of the whole directory.
+    pub fn resolve(
+        &self,
+        manifest: Option<&Manifest>,
+        filename: &str,
+        base_directory_string: &str,
+    ) -> ResolvedFiles {
+        let resolved = resolve_files(manifest, filename, base_directory_string);
+        if resolved.from_fallback && filename == self.dependencies_file {
+            return ResolvedFiles {
+                files: vec![filename.to_string()],
+                aliases: Vec::new(),
+                from_fallback: false,
+                dirty_check: resolved.dirty_check,
+            };
+        }
+        resolved
+    }
+
+    /// Finds the latest commit of a file and its dependencies and checks them for uncommitted
+    /// changes, like the command line tool does for a single file. A directory is monitored
+    /// as a whole, unless the dependencies file in it has an entry for it.
+    pub fn query(&self, file: &Path) -> Result<Query, String> {
+        let path = file
+            .canonicalize()
+            .map_err(|e| format!("Invalid file {}: {e}", file.display()))?;
+        let base_directory = if path.is_dir() {
+            path.as_path()
+        } else {
+            path.parent()
+                .ok_or_else(|| format!("No directory for {}", path.display()))?
+        };
+        let base_directory_string = base_directory
+            .to_str()
+            .ok_or_else(|| format!("Not valid UTF-8: {}", base_directory.display()))?;
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!("No valid filename: {}", path.display()))?;
+
+        let repository = self.open_repository(base_directory)?;
+        let manifest = Manifest::load(&self.dependencies_path(base_directory))?;
+        let resolved = self.resolve(manifest.as_ref(), filename, base_directory_string);
+
+        let commit = repository.latest_commit(&resolved.history_files());
+        let dirty = (self.dirty_check && resolved.dirty_check)
+            .then(|| !repository.is_clean(&resolved.files));
+        Ok(Query {
+            resolved,
+            commit,
+            dirty,
+        })
+    }
+}
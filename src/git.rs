@@ -0,0 +1,1104 @@
+use crate::vcs::{normalize_path, CommitInfo, Diffstat, Identity, VersionControl};
+use log::debug;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, BufReader, Split, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdout, Command, Stdio},
+};
+
+/// Format of the `git status --porcelain` output
+#[derive(Clone, Copy)]
+pub enum StatusFormat {
+    V1,
+    V2,
+}
+
+impl StatusFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "v1" => Ok(StatusFormat::V1),
+            "v2" => Ok(StatusFormat::V2),
+            _ => Err(format!(
+                "Invalid status format: {value} (expected v1 or v2)"
+            )),
+        }
+    }
+
+    fn arg(self) -> &'static str {
+        match self {
+            StatusFormat::V1 => "--porcelain=v1",
+            StatusFormat::V2 => "--porcelain=v2",
+        }
+    }
+}
+
+/// One changed path reported by `git status`
+pub struct StatusEntry {
+    /// Two letter status code (XY), `??` for untracked and `!!` for ignored files
+    pub code: String,
+    pub path: String,
+}
+
+/// Parses the output of `git status --porcelain=<format> -z` into its entries.
+/// See https://git-scm.com/docs/git-status#_porcelain_format_version_1
+pub fn parse_status(output: &str, format: StatusFormat) -> Vec<StatusEntry> {
+    let mut records = output.split('\0');
+    let mut entries = Vec::new();
+    while let Some(record) = records.next() {
+        if has_original_path(record, format) {
+            records.next();
+        }
+        entries.extend(parse_status_record(record, format));
+    }
+    entries
+}
+
+/// Parses one NUL terminated record of the `git status --porcelain=<format> -z` output, None for
+/// headers. With `-z`, paths are never quoted, and the original path of a rename or copy is a
+/// record of its own, see `has_original_path`.
+pub fn parse_status_record(record: &str, format: StatusFormat) -> Option<StatusEntry> {
+    match format {
+        StatusFormat::V1 => {
+            let (code, path) = (record.get(..2)?, record.get(3..)?);
+            Some(StatusEntry {
+                code: code.to_string(),
+                path: path.to_string(),
+            })
+        }
+        StatusFormat::V2 => {
+            // The number of fields before the path depends on the kind of entry
+            let (code, fields) = match record.split_once(' ')? {
+                ("1", rest) => (rest.get(..2)?, 8),
+                ("2", rest) => (rest.get(..2)?, 9),
+                ("u", rest) => (rest.get(..2)?, 10),
+                ("?", _) => ("??", 1),
+                ("!", _) => ("!!", 1),
+                _ => return None, // headers
+            };
+            let path = record.splitn(fields + 1, ' ').last()?;
+            Some(StatusEntry {
+                code: code.to_string(),
+                path: path.to_string(),
+            })
+        }
+    }
+}
+
+/// Whether the record is a rename or copy, which is followed by a record with the original path
+fn has_original_path(record: &str, format: StatusFormat) -> bool {
+    match format {
+        StatusFormat::V1 => record
+            .get(..2)
+            .is_some_and(|code| code.contains(['R', 'C'])),
+        StatusFormat::V2 => record.starts_with("2 "),
+    }
+}
+
+/// The entries of a running `git status`, parsed as its output arrives.
+/// Dropping the iterator early stops git, e.g. once the first change is found.
+pub struct StatusIter {
+    child: Child,
+    /// The NUL terminated records as bytes, as paths don't have to be valid UTF-8
+    records: Split<BufReader<ChildStdout>>,
+    format: StatusFormat,
+}
+
+impl StatusIter {
+    /// Parses the `git status --porcelain=<format> -z` output of the running child
+    fn new(mut child: Child, format: StatusFormat) -> Self {
+        let stdout = child.stdout.take().expect("Failed to read git output");
+        StatusIter {
+            child,
+            records: BufReader::new(stdout).split(b'\0'),
+            format,
+        }
+    }
+}
+
+impl Iterator for StatusIter {
+    type Item = StatusEntry;
+
+    fn next(&mut self) -> Option<StatusEntry> {
+        loop {
+            let record = self.records.next()?.expect("Failed to read git output");
+            let record = String::from_utf8_lossy(&record);
+            if has_original_path(&record, self.format) {
+                self.records.next();
+            }
+            if let Some(entry) = parse_status_record(&record, self.format) {
+                debug!("Uncommitted change: {} {}", entry.code, entry.path);
+                return Some(entry);
+            }
+        }
+    }
+}
+
+impl Drop for StatusIter {
+    fn drop(&mut self) {
+        // git may still be writing, it has already exited if the output was read completely
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// Fields separated by the unit separator: the hashes, then the committer and the author, each
+/// with the date in short format (cs, as), as Unix timestamp (ct, at), name and email:
+/// https://git-scm.com/docs/pretty-formats
+const COMMIT_FORMAT: &str =
+    "--pretty=format:%H%x1f%h%x1f%cs%x1f%ct%x1f%cn%x1f%ce%x1f%as%x1f%at%x1f%an%x1f%ae";
+
+/// Parses a commit printed with `COMMIT_FORMAT` with the date and person of the identity,
+/// None if empty
+fn parse_commit(output: &str, identity: Identity) -> Option<CommitInfo> {
+    let fields: Vec<&str> = output.split('\x1f').collect();
+    let [hash, short, cs, ct, cn, ce, author_date, at, an, ae] = fields[..] else {
+        return None;
+    };
+    if hash.is_empty() {
+        return None;
+    }
+    let (date, timestamp, name, email) = match identity {
+        Identity::Author => (author_date, at, an, ae),
+        Identity::AuthorOrCommitter if !an.is_empty() => (author_date, at, an, ae),
+        Identity::Committer | Identity::AuthorOrCommitter => (cs, ct, cn, ce),
+    };
+    Some(CommitInfo {
+        hash: hash.to_string(),
+        short: short.to_string(),
+        date: date.to_string(),
+        timestamp: timestamp.parse().expect("Invalid commit timestamp"),
+        name: name.to_string(),
+        email: email.to_string(),
+    })
+}
+
+/// The groups of paths (relative to the root, the empty path is the root itself) by path, to find
+/// the groups containing a changed file without comparing it to every path
+struct GroupIndex<'a> {
+    groups: HashMap<&'a str, Vec<usize>>,
+}
+
+impl<'a> GroupIndex<'a> {
+    fn new(groups: &'a [Vec<String>]) -> Self {
+        let mut index: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (group, paths) in groups.iter().enumerate() {
+            for path in paths {
+                index.entry(path.as_str()).or_default().push(group);
+            }
+        }
+        GroupIndex { groups: index }
+    }
+
+    /// The groups containing the file (relative to the root) itself or one of its directories
+    fn containing<'b>(&'b self, name: &'b str) -> impl Iterator<Item = usize> + 'b {
+        let directories = name.rmatch_indices('/').map(|(end, _)| &name[..end]);
+        std::iter::once(name)
+            .chain(directories)
+            .chain(std::iter::once(""))
+            .filter_map(|path| self.groups.get(path))
+            .flatten()
+            .copied()
+    }
+}
+
+/// Above this total length of the paths, a walk of the history is not limited to them, as the
+/// command line could exceed the limit of the operating system (`E2BIG`)
+const MAX_PATHSPEC_BYTES: usize = 64 * 1024;
+
+/// Mode of index entries that record the commit of a submodule
+const GITLINK_MODE: &str = "160000";
+
+/// Config settings that change the output parsed by this tool, pinned to git's defaults
+/// (or to a parseable value), so the user's config can't interfere
+pub const PINNED_CONFIG: &[&str] = &[
+    "core.abbrev=auto",
+    "core.quotePath=false",
+    "color.ui=false",
+    "log.showSignature=false",
+    "log.follow=false",
+    "i18n.logOutputEncoding=UTF-8",
+    "diff.renames=true",
+    "diff.ignoreSubmodules=none",
+    "status.showUntrackedFiles=normal",
+];
+
+/// A git repository, queried by running the git command line tool in `cwd`
+pub struct Repository {
+    cwd: PathBuf,
+    /// Format of the `git status` output used for the dirty check
+    pub status_format: StatusFormat,
+    /// Only consider staged changes for the dirty check
+    pub staged_only: bool,
+    /// Include the commits of submodules recorded in the index in the fingerprint
+    pub include_submodule_pointers: bool,
+    /// Include ignored files matching the monitored files in the working tree fingerprint
+    pub include_ignored: bool,
+    /// Only consider commits changing the files in these ways, as in `git log --diff-filter`
+    pub diff_filter: Option<String>,
+    /// Revision whose history is searched for the latest commit, HEAD if None
+    pub revision: Option<String>,
+    /// Override the config settings in `PINNED_CONFIG` for all git commands
+    pub isolated: bool,
+    /// Run all git commands with `--no-optional-locks`, so `git status` doesn't lock the index
+    /// to refresh it, which would block or fail concurrent git commands
+    pub no_optional_locks: bool,
+    /// Whose date, name and email are reported for the commits
+    pub identity: Identity,
+}
+
+impl Repository {
+    /// A repository in `cwd` with the default settings, without checking that there is one
+    pub fn new(cwd: &Path) -> Self {
+        Repository {
+            cwd: cwd.to_path_buf(),
+            status_format: StatusFormat::V2,
+            staged_only: false,
+            include_submodule_pointers: false,
+            include_ignored: false,
+            diff_filter: None,
+            revision: None,
+            isolated: true,
+            no_optional_locks: false,
+            identity: Identity::Committer,
+        }
+    }
+
+    /// Checks if `cwd` is inside a git work tree.
+    /// Theoretically redundant, only for nicer error messages.
+    pub fn open(cwd: &Path) -> Result<Self, String> {
+        Self::new(cwd).verify()
+    }
+
+    /// Checks if `cwd` is inside a git work tree, running git with the settings of the repository
+    pub fn verify(self) -> Result<Self, String> {
+        let output = self
+            .command(&self.cwd)
+            .arg("rev-parse")
+            .arg("--is-inside-work-tree")
+            .output()
+            .expect("Failed to execute git command");
+
+        if output.status.success() {
+            Ok(self)
+        } else {
+            Err("Not a git repository (or any of the parent directories): .git".to_string())
+        }
+    }
+
+    /// The latest commits affecting the files in the history of the revision, newest first.
+    /// Errors, e.g. an unknown revision, are treated as no commits.
+    fn log(&self, files: &[String], revision: &str, count: usize) -> Vec<CommitInfo> {
+        let mut command = self.command(&self.cwd);
+        command
+            .arg("log")
+            .arg(format!("--max-count={count}"))
+            .arg(COMMIT_FORMAT);
+        if let Some(filter) = &self.diff_filter {
+            command.arg(format!("--diff-filter={filter}"));
+        }
+        let output = command
+            .arg("--end-of-options")
+            .arg(revision)
+            .arg("--")
+            .args(files)
+            .output()
+            .expect("Failed to execute git command");
+
+        if output.status.success() {
+            // Empty output means there is no commit
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| parse_commit(line, self.identity))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// The paths of the groups relative to the repository root, as git lists changed files.
+    /// The root itself is the empty path. Paths outside of the root are an error, git would reject
+    /// them.
+    fn relative_to_root(
+        &self,
+        root: &Path,
+        groups: &[Vec<PathBuf>],
+    ) -> Result<Vec<Vec<String>>, String> {
+        let relative = |path: &PathBuf| -> Result<String, String> {
+            // Normalized, git lists `a.txt`, not `sub/../a.txt`
+            let path = normalize_path(&self.cwd.join(path));
+            match path.strip_prefix(root) {
+                Ok(relative) => Ok(relative.to_string_lossy().into_owned()),
+                Err(_) => Err(format!(
+                    "{} is outside of the repository {}",
+                    path.display(),
+                    root.display()
+                )),
+            }
+        };
+        groups
+            .iter()
+            .map(|paths| paths.iter().map(relative).collect())
+            .collect()
+    }
+
+    /// Walks `git log --name-only` in `root` from the newest commit of the revision and calls
+    /// `visit` with the position of each commit (0 is the newest), its `COMMIT_FORMAT` header and
+    /// the changed files relative to the root, until it returns false. Then git is stopped, so old
+    /// history is usually never read. The walk is limited to the paths, unless there are too many
+    /// for the command line (see `MAX_PATHSPEC_BYTES`), so `visit` has to filter the files itself.
+    /// It is an error if git fails, e.g. for an invalid path, as the visited commits are incomplete.
+    fn walk(
+        &self,
+        root: &Path,
+        paths: &[&str],
+        mut visit: impl FnMut(usize, &str, &[&str]) -> bool,
+    ) -> Result<(), String> {
+        let format = COMMIT_FORMAT.replace("format:", "format:%x1e");
+        let mut command = self.command(root);
+        // Without rename detection, both paths of a rename are listed, as for the per path query
+        command.args(["log", "-z", "--name-only", "--no-renames", &format]);
+        if let Some(filter) = &self.diff_filter {
+            command.arg(format!("--diff-filter={filter}"));
+        }
+        command
+            .arg("--end-of-options")
+            .arg(self.revision.as_deref().unwrap_or("HEAD"))
+            .arg("--");
+        if paths.iter().map(|path| path.len() + 1).sum::<usize>() > MAX_PATHSPEC_BYTES {
+            command.arg(".");
+        } else {
+            // An empty path is the root itself
+            command.args(
+                paths
+                    .iter()
+                    .map(|path| if path.is_empty() { "." } else { path }),
+            );
+        }
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to execute git command");
+        let stdout = child.stdout.take().expect("Failed to read git output");
+
+        let mut position = 0;
+        for record in BufReader::new(stdout).split(0x1e) {
+            let record = record.expect("Failed to read git output");
+            let record = String::from_utf8_lossy(&record);
+            let Some((header, names)) = record.split_once('\n') else {
+                continue; // before the first commit
+            };
+            let names: Vec<&str> = names.split('\0').filter(|name| !name.is_empty()).collect();
+            if !visit(position, header, &names) {
+                // Stopped early, so the exit status is that of the killed process
+                child.kill().ok();
+                child.wait().ok();
+                return Ok(());
+            }
+            position += 1;
+        }
+        let status = child.wait().expect("Failed to execute git command");
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("git log failed in {} ({status})", root.display()))
+        }
+    }
+
+    /// Walks the complete history of the revision once and finds the latest commit of every path
+    /// that was ever changed in it, including deleted files and the old paths of renamed files,
+    /// with the position of the commit in the walk (0 is the newest). The paths are relative to
+    /// the root and sorted.
+    pub fn latest_commits_of_all_paths(
+        &self,
+    ) -> Result<BTreeMap<String, (usize, CommitInfo)>, String> {
+        let root = self.repo_root()?;
+        let mut latest = BTreeMap::new();
+        self.walk(&root, &[""], |rank, header, names| {
+            for name in names {
+                if !latest.contains_key(*name) {
+                    if let Some(commit) = parse_commit(header, self.identity) {
+                        latest.insert(name.to_string(), (rank, commit));
+                    }
+                }
+            }
+            true
+        })?;
+        Ok(latest)
+    }
+
+    /// Parses a `--diff-filter` value: change types in uppercase to select, in lowercase to exclude them.
+    /// git ignores the exclusions if no diff is generated (as for the commit query), so they are
+    /// resolved to the selected types here. See `--diff-filter` in https://git-scm.com/docs/git-log
+    pub fn parse_diff_filter(filter: &str) -> Result<String, String> {
+        const CHANGE_TYPES: &str = "ACDMRTUXB";
+        if filter.is_empty() {
+            return Err("Empty diff filter".to_string());
+        }
+        if let Some(c) = filter
+            .chars()
+            .find(|c| !CHANGE_TYPES.contains(c.to_ascii_uppercase()))
+        {
+            return Err(format!(
+                "Invalid diff filter character: {c} (expected any of {CHANGE_TYPES}, or lowercase to exclude)"
+            ));
+        }
+
+        // Without any selected type, all types but the excluded ones are selected
+        let selected: String = filter.chars().filter(char::is_ascii_uppercase).collect();
+        let selected = if selected.is_empty() {
+            CHANGE_TYPES
+        } else {
+            &selected
+        };
+        let resolved: String = selected
+            .chars()
+            .filter(|c| !filter.contains(c.to_ascii_lowercase()))
+            .collect();
+        if resolved.is_empty() {
+            return Err(format!("Diff filter {filter} excludes all change types"));
+        }
+        Ok(resolved)
+    }
+
+    /// A git command running in `cwd`, isolated from the user's config if enabled
+    fn command(&self, cwd: &Path) -> Command {
+        let mut command = Command::new("git");
+        command.current_dir(cwd);
+        if self.no_optional_locks {
+            command.arg("--no-optional-locks");
+        }
+        if self.isolated {
+            for setting in PINNED_CONFIG {
+                command.arg("-c").arg(setting);
+            }
+        }
+        command
+    }
+
+    /// Runs git with the given arguments and returns its trimmed stdout, or stderr as error
+    fn run(&self, cwd: &Path, args: &[&str], stdin: Option<&[u8]>) -> Result<String, String> {
+        let mut command = self.command(cwd);
+        command
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            });
+        let mut child = command.spawn().expect("Failed to execute git command");
+        let pipe = child.stdin.take();
+        // The input is written by another thread while the output is read, as git may answer
+        // each line (e.g. `check-ignore --stdin`) and block on a full stdout pipe otherwise
+        let (output, written) = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || match (stdin, pipe) {
+                (Some(input), Some(mut pipe)) => pipe.write_all(input),
+                _ => Ok(()),
+            });
+            let output = child
+                .wait_with_output()
+                .expect("Failed to execute git command");
+            (
+                output,
+                writer.join().expect("Failed to write to git command"),
+            )
+        });
+
+        if output.status.success() {
+            // Exited successfully without reading all of the input, so the output is incomplete
+            written.map_err(|e| format!("Failed to write to git command: {e}"))?;
+            Ok(String::from_utf8_lossy(&output.stdout)
+                .trim_end()
+                .to_string())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string())
+        }
+    }
+
+    /// Streams the uncommitted changes of the files, see `StatusIter`.
+    /// The entries are identical for both status formats.
+    pub fn status_iter(&self, files: &[String]) -> StatusIter {
+        self.status_iter_in(&self.cwd, files)
+    }
+
+    /// Like `status_iter`, but runs git in `cwd`, which the paths are relative to
+    fn status_iter_in(&self, cwd: &Path, files: &[String]) -> StatusIter {
+        let child = self
+            .command(cwd)
+            .arg("status")
+            .arg(self.status_format.arg()) // stable scripting interface
+            .arg("-z") // unquoted paths
+            .args(files)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to execute git command");
+        StatusIter::new(child, self.status_format)
+    }
+
+    /// Lists the files with uncommitted changes, i.e., the working tree is clean if there are none.
+    /// The paths are relative to `cwd`, like those of `git status`.
+    fn working_tree_changes(&self, cwd: &Path, files: &[String]) -> Vec<String> {
+        self.status_iter_in(cwd, files)
+            .map(|entry| entry.path)
+            .collect()
+    }
+
+    /// Lists the files with staged changes. Unstaged and untracked changes are ignored.
+    /// The paths are relative to the root.
+    fn index_changes(&self, files: &[String]) -> Vec<String> {
+        let output = self
+            .command(&self.cwd)
+            .arg("diff")
+            .arg("--cached")
+            .arg("--name-only")
+            .arg("-z") // unquoted paths
+            .arg("--")
+            .args(files)
+            .output()
+            .expect("Failed to execute git command");
+
+        String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Lists the index entries of the matching files as `(mode, object hash, path)`
+    fn index_entries(&self, files: &[String]) -> Result<Vec<(String, String, String)>, String> {
+        // NUL separated, so paths are not quoted
+        let mut args = vec!["ls-files", "--stage", "-z", "--"];
+        args.extend(files.iter().map(String::as_str));
+        // <mode> SP <object> SP <stage> TAB <path>
+        Ok(self
+            .run(&self.cwd, &args, None)?
+            .split('\0')
+            .filter_map(|line| {
+                let (info, path) = line.split_once('\t')?;
+                let mut info = info.split(' ');
+                let (mode, object) = (info.next()?, info.next()?);
+                Some((mode.to_string(), object.to_string(), path.to_string()))
+            })
+            .collect())
+    }
+}
+
+impl VersionControl for Repository {
+    fn latest_commit(&self, files: &[String]) -> Option<CommitInfo> {
+        self.latest_commit_at(files, self.revision.as_deref().unwrap_or("HEAD"))
+    }
+
+    fn latest_commit_at(&self, files: &[String], revision: &str) -> Option<CommitInfo> {
+        self.log(files, revision, 1).into_iter().next()
+    }
+
+    fn recent_commits(&self, files: &[String], count: usize) -> Vec<CommitInfo> {
+        self.log(files, self.revision.as_deref().unwrap_or("HEAD"), count)
+    }
+
+    /// Walks `git log --name-only` from the newest commit and assigns each commit to the groups
+    /// that contain one of its changed files (or a directory of it). The walk, and git, is stopped
+    /// as soon as every group has its commit, so old history is usually never read.
+    fn ranked_latest_commits(
+        &self,
+        groups: &[Vec<PathBuf>],
+    ) -> Result<Vec<Option<(usize, CommitInfo)>>, String> {
+        let root = self.repo_root()?;
+        let groups = self.relative_to_root(&root, groups)?;
+        let mut commits: Vec<Option<(usize, CommitInfo)>> = groups.iter().map(|_| None).collect();
+        if groups.is_empty() {
+            return Ok(commits);
+        }
+
+        let index = GroupIndex::new(&groups);
+        let paths: Vec<&str> = groups.iter().flatten().map(String::as_str).collect();
+        let mut remaining = groups.len();
+        self.walk(&root, &paths, |rank, header, names| {
+            let mut affected: Vec<usize> = names
+                .iter()
+                .flat_map(|name| index.containing(name))
+                .collect();
+            // The root is affected by every commit, even one listing no files (e.g. a merge)
+            if names.is_empty() {
+                affected.extend(index.groups.get("").into_iter().flatten());
+            }
+            for group in affected {
+                if commits[group].is_none() {
+                    commits[group] = parse_commit(header, self.identity).map(|info| (rank, info));
+                    remaining -= 1;
+                }
+            }
+            remaining > 0
+        })?;
+        Ok(commits)
+    }
+
+    /// Runs the dirty check once for the paths of all groups, instead of once per group, and
+    /// assigns each changed file to the groups containing it (or a directory of it)
+    fn dirty_groups(&self, groups: &[Vec<PathBuf>]) -> Result<Vec<bool>, String> {
+        let root = self.repo_root()?;
+        let relative_groups = self.relative_to_root(&root, groups)?;
+        // Absolute paths, and git runs in the root, so the changed files are listed relative to
+        // it like the groups. Shared dependencies are passed only once.
+        let mut paths: Vec<String> = Vec::new();
+        for path in groups.iter().flatten() {
+            let path = self.cwd.join(path).to_string_lossy().into_owned();
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let changed = if self.staged_only {
+            self.index_changes(&paths)
+        } else {
+            self.working_tree_changes(&root, &paths)
+        };
+        let index = GroupIndex::new(&relative_groups);
+        let mut dirty = vec![false; groups.len()];
+        for group in changed.iter().flat_map(|name| index.containing(name)) {
+            dirty[group] = true;
+        }
+        Ok(dirty)
+    }
+
+    /// Walks the commits of all refs that add or remove the blob (`git log --all --find-object`)
+    /// and returns the latest one whose changes contain it as new content.
+    fn latest_commit_with_blob(&self, blob: &str) -> Option<CommitInfo> {
+        let find_object = format!("--find-object={blob}");
+        // Each commit hash is preceded by a record separator and followed by its raw diff lines
+        let output = self
+            .run(
+                &self.cwd,
+                &[
+                    "log",
+                    "--all",
+                    "--raw",
+                    "--no-abbrev",
+                    "--pretty=format:%x1e%H",
+                    &find_object,
+                ],
+                None,
+            )
+            .ok()?;
+        let hash = output.split('\x1e').find_map(|entry| {
+            let (hash, raw) = entry.split_once('\n')?;
+            // :<old mode> <new mode> <old blob> <new blob> <status>\t<path>
+            raw.lines()
+                .any(|line| line.split(' ').nth(3) == Some(blob))
+                .then_some(hash)
+        })?;
+        // Queried separately, as --no-abbrev applies to the short hash as well
+        let commit = self
+            .run(&self.cwd, &["log", "-1", COMMIT_FORMAT, hash], None)
+            .ok()?;
+        parse_commit(&commit, self.identity)
+    }
+
+    fn dirty_files(&self, files: &[String]) -> Vec<String> {
+        if self.staged_only {
+            self.index_changes(files)
+        } else {
+            self.working_tree_changes(&self.cwd, files)
+        }
+    }
+
+    /// Stops at the first uncommitted change instead of listing all of them
+    fn is_clean(&self, files: &[String]) -> bool {
+        if self.staged_only {
+            self.index_changes(files).is_empty()
+        } else {
+            self.status_iter(files).next().is_none()
+        }
+    }
+
+    /// For a single directory, this is the hash of its tree object at HEAD (`git rev-parse HEAD:<dir>`).
+    /// Any other set of files has no tree object of its own, so the `git ls-tree` listing (mode, type,
+    /// blob hash and path) of the matching files at HEAD is hashed instead (`git hash-object --stdin`).
+    fn tree_hash(&self, files: &[String]) -> Result<String, String> {
+        if let [directory] = files {
+            let directory = self.cwd.join(directory);
+            if directory.is_dir() {
+                return self.run(&directory, &["rev-parse", "HEAD:./"], None);
+            }
+        }
+
+        // Expand the pathspecs (wildcards, excludes) to the paths they match, NUL separated, so
+        // they are not quoted
+        let mut args = vec!["ls-files", "-z", "--"];
+        args.extend(files.iter().map(String::as_str));
+        let paths = self.run(&self.cwd, &args, None)?;
+        if paths.is_empty() {
+            return Err("No tracked files match the monitored files".to_string());
+        }
+
+        let mut args = vec!["ls-tree", "HEAD", "--"];
+        args.extend(paths.split('\0').filter(|path| !path.is_empty()));
+        let listing = self.run(&self.cwd, &args, None)?;
+
+        self.run(
+            &self.cwd,
+            &["hash-object", "--stdin"],
+            Some(listing.as_bytes()),
+        )
+    }
+
+    /// The `<blob hash>\t<path>` listing of the matching files is hashed (`git hash-object --stdin`).
+    /// The blob hashes are taken from the index (`git ls-files --stage`) if `staged` is set, otherwise
+    /// the files in the working tree are hashed, including untracked but not ignored files.
+    /// Ignored files are hashed as well if `include_ignored` is set.
+    /// If `include_submodule_pointers` is set, the `<commit hash>\t<path>` of each submodule recorded
+    /// in the index is appended to the listing.
+    fn fingerprint(&self, files: &[String], staged: bool) -> Result<String, String> {
+        let index_entries = self.index_entries(files)?;
+
+        let mut listing: Vec<String> = if staged {
+            index_entries
+                .iter()
+                .filter(|(mode, _, _)| mode != GITLINK_MODE)
+                .map(|(_, blob, path)| format!("{blob}\t{path}"))
+                .collect()
+        } else {
+            // NUL separated, so paths are not quoted
+            let mut args = vec![
+                "ls-files",
+                "-z",
+                "--cached",
+                "--others",
+                "--exclude-standard",
+                "--",
+            ];
+            args.extend(files.iter().map(String::as_str));
+            let mut output = self.run(&self.cwd, &args, None)?;
+            if self.include_ignored {
+                let mut args = vec![
+                    "ls-files",
+                    "-z",
+                    "--others",
+                    "--ignored",
+                    "--exclude-standard",
+                    "--",
+                ];
+                args.extend(files.iter().map(String::as_str));
+                output.push('\0');
+                output.push_str(&self.run(&self.cwd, &args, None)?);
+            }
+            // Deleted files are still in the index, but not part of the content anymore.
+            // Submodules are directories, so they are skipped as well.
+            let mut paths: Vec<&str> = output
+                .split('\0')
+                .filter(|path| !path.is_empty() && self.cwd.join(path).is_file())
+                .collect();
+            paths.sort_unstable();
+            paths.dedup(); // unmerged files are listed per stage
+
+            if paths.is_empty() {
+                Vec::new()
+            } else {
+                let mut args = vec!["hash-object", "--"];
+                args.extend(&paths);
+                let blobs = self.run(&self.cwd, &args, None)?;
+                blobs
+                    .lines()
+                    .zip(&paths)
+                    .map(|(blob, path)| format!("{blob}\t{path}"))
+                    .collect()
+            }
+        };
+
+        if self.include_submodule_pointers {
+            listing.extend(
+                index_entries
+                    .iter()
+                    .filter(|(mode, _, _)| mode == GITLINK_MODE)
+                    .map(|(_, commit, path)| format!("{commit}\t{path}")),
+            );
+        }
+
+        if listing.is_empty() {
+            return Err("No files match the monitored files".to_string());
+        }
+        debug!("Fingerprint of: {:#?}", listing);
+
+        self.run(
+            &self.cwd,
+            &["hash-object", "--stdin"],
+            Some(listing.join("\n").as_bytes()),
+        )
+    }
+
+    fn list_files(&self, files: &[String]) -> Result<Vec<PathBuf>, String> {
+        // NUL separated, so paths are not quoted
+        let mut args = vec!["ls-files", "-z", "--"];
+        args.extend(files.iter().map(String::as_str));
+        Ok(self
+            .run(&self.cwd, &args, None)?
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(|path| normalize_path(&self.cwd.join(path)))
+            .collect())
+    }
+
+    fn ignored_files(&self, files: &[String]) -> Vec<String> {
+        // Exit code 1 means none of the files is ignored. Tracked files are never reported.
+        // The paths are passed on stdin, so any number of them is checked by one command, and
+        // NUL separated, so they are not quoted.
+        let mut input = Vec::new();
+        for file in files {
+            input.extend_from_slice(file.as_bytes());
+            input.push(b'\0');
+        }
+        self.run(&self.cwd, &["check-ignore", "--stdin", "-z"], Some(&input))
+            .map(|output| {
+                output
+                    .split('\0')
+                    .filter(|path| !path.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn committed_blob(&self, path: &str) -> Option<String> {
+        let object = format!("HEAD:{path}");
+        self.run(
+            &self.cwd,
+            &["rev-parse", "--verify", "--quiet", &object],
+            None,
+        )
+        .ok()
+    }
+
+    fn content_blob(&self, path: &str) -> Option<String> {
+        let root = self.repo_root().ok()?;
+        if !root.join(path).is_file() {
+            return None;
+        }
+        self.run(&root, &["hash-object", "--", path], None).ok()
+    }
+
+    fn repo_root(&self) -> Result<PathBuf, String> {
+        let output = self
+            .command(&self.cwd)
+            .arg("rev-parse")
+            .arg("--show-toplevel")
+            .output()
+            .expect("Failed to execute git command");
+
+        if output.status.success() {
+            Ok(PathBuf::from(
+                String::from_utf8_lossy(&output.stdout).trim_end(),
+            ))
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string())
+        }
+    }
+
+    fn diffstat(&self, commit: &str, files: &[String]) -> Result<Diffstat, String> {
+        // A merge commit is compared to its first parent, as it has no diff by default
+        let mut args = vec![
+            "show",
+            "--shortstat",
+            "--format=",
+            "--diff-merges=first-parent",
+            "--end-of-options",
+            commit,
+            "--",
+        ];
+        args.extend(files.iter().map(String::as_str));
+        let summary = self.run(&self.cwd, &args, None)?;
+
+        // E.g. ` 3 files changed, 10 insertions(+), 2 deletions(-)`, parts with 0 are omitted and
+        // nothing is printed if the commit didn't change the files
+        let mut diffstat = Diffstat {
+            files_changed: 0,
+            insertions: 0,
+            deletions: 0,
+        };
+        for part in summary
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let (count, kind) = part
+                .split_once(' ')
+                .ok_or_else(|| format!("Unexpected git diffstat: {summary}"))?;
+            let count = count
+                .parse()
+                .map_err(|_| format!("Unexpected git diffstat: {summary}"))?;
+            if kind.starts_with("file") {
+                diffstat.files_changed = count;
+            } else if kind.starts_with("insertion") {
+                diffstat.insertions = count;
+            } else if kind.starts_with("deletion") {
+                diffstat.deletions = count;
+            }
+        }
+        Ok(diffstat)
+    }
+
+    fn commit_count(&self, from: &str, to: &str) -> Result<u64, String> {
+        let range = format!("{from}..{to}");
+        let count = self.run(
+            &self.cwd,
+            &["rev-list", "--count", "--end-of-options", &range],
+            None,
+        )?;
+        count
+            .parse()
+            .map_err(|_| format!("Unexpected output of git rev-list: {count}"))
+    }
+
+    fn current_branch(&self) -> Result<Option<String>, String> {
+        // A detached HEAD is abbreviated to HEAD, which can't be the name of a branch
+        let branch = self.run(&self.cwd, &["rev-parse", "--abbrev-ref", "HEAD"], None)?;
+        Ok(Some(branch).filter(|branch| branch != "HEAD"))
+    }
+
+    fn resolve_commit(&self, revision: &str) -> Result<String, String> {
+        let commit = format!("{revision}^{{commit}}");
+        self.run(
+            &self.cwd,
+            &["rev-parse", "--verify", "--end-of-options", &commit],
+            None,
+        )
+    }
+
+    fn first_parent(&self, commit: &str) -> Result<Option<String>, String> {
+        // The commit followed by its parents, `<commit>^` would fail for a root commit
+        let commits = self.run(
+            &self.cwd,
+            &[
+                "rev-list",
+                "--parents",
+                "--max-count=1",
+                "--end-of-options",
+                commit,
+            ],
+            None,
+        )?;
+        Ok(commits.split_whitespace().nth(1).map(str::to_string))
+    }
+
+    fn merge_base(&self, commit: &str, reference: &str) -> Result<Option<String>, String> {
+        // Exit code 1 without output means there is no common ancestor, errors are reported on stderr
+        match self.run(
+            &self.cwd,
+            &["merge-base", "--end-of-options", commit, reference],
+            None,
+        ) {
+            Ok(base) => Ok(Some(base)),
+            Err(stderr) if stderr.is_empty() => Ok(None),
+            Err(stderr) => Err(stderr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(output: &str, format: StatusFormat) -> Vec<(String, String)> {
+        parse_status(output, format)
+            .into_iter()
+            .map(|entry| (entry.code, entry.path))
+            .collect()
+    }
+
+    #[test]
+    fn v1_entries() {
+        let entries = |output| entries(output, StatusFormat::V1);
+        assert_eq!(
+            entries(" M a b.txt\0?? q\"uote.txt\0"),
+            vec![
+                (" M".into(), "a b.txt".into()),
+                ("??".into(), "q\"uote.txt".into())
+            ]
+        );
+        // The original path of a rename is the next record
+        assert_eq!(
+            entries("R  new.txt\0old.txt\0 M other.txt\0"),
+            vec![
+                ("R ".into(), "new.txt".into()),
+                (" M".into(), "other.txt".into())
+            ]
+        );
+        assert_eq!(entries(""), vec![]);
+    }
+
+    #[test]
+    fn v2_entries() {
+        let entries = |output| entries(output, StatusFormat::V2);
+        assert_eq!(
+            entries(
+                "# branch.oid 1234567\x001 .M N... 100644 100644 100644 1234567 1234567 a b.txt\0"
+            ),
+            vec![(".M".into(), "a b.txt".into())]
+        );
+        assert_eq!(
+            entries("2 R. N... 100644 100644 100644 1234567 1234567 R100 new.txt\0old.txt\0? q\"uote.txt\0"),
+            vec![
+                ("R.".into(), "new.txt".into()),
+                ("??".into(), "q\"uote.txt".into())
+            ]
+        );
+        assert_eq!(
+            entries("u UU N... 100644 100644 100644 100644 1234567 1234567 1234567 both.txt\0"),
+            vec![("UU".into(), "both.txt".into())]
+        );
+        assert_eq!(
+            entries("! build/out\0"),
+            vec![("!!".into(), "build/out".into())]
+        );
+    }
+
+    #[test]
+    fn diff_filter_resolves_exclusions() {
+        let parse = Repository::parse_diff_filter;
+        // Lowercase only: all types but the excluded one
+        assert_eq!(parse("a").unwrap(), "CDMRTUXB");
+        assert_eq!(parse("ad").unwrap(), "CMRTUXB");
+        assert_eq!(parse("AD").unwrap(), "AD");
+        // Mixed case: the selected types without the excluded ones
+        assert_eq!(parse("AMm").unwrap(), "A");
+        assert_eq!(parse("MAd").unwrap(), "MA");
+        assert!(parse("Aa").unwrap_err().contains("excludes all"));
+        assert!(parse("AZ")
+            .unwrap_err()
+            .contains("Invalid diff filter character: Z"));
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn dropping_the_status_iter_stops_git() {
+        // Stands in for a git status with an endless number of changes
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg("while :; do printf '1 .M N... 100644 100644 100644 1234567 1234567 a.txt\\0'; done")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        let mut entries = StatusIter::new(child, StatusFormat::V2);
+        let entry = entries.next().unwrap();
+        assert_eq!((entry.code.as_str(), entry.path.as_str()), (".M", "a.txt"));
+
+        drop(entries);
+        let running = Command::new("sh")
+            .arg("-c")
+            .arg(format!("kill -0 {pid} 2>/dev/null"))
+            .status()
+            .unwrap()
+            .success();
+        assert!(!running);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    config::Config,
+    vcs::{normalize_path, CommitInfo, Identity, VersionControl},
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Version of the index file format, an index of another version has to be rebuilt.
+/// Version 1 only contained the files tracked at the indexed commit.
+const VERSION: i64 = 2;
+
+/// The latest commit of every path in the history of one commit, found in a single walk of the
+/// history (see [`crate::git::Repository::latest_commits_of_all_paths`]). Deleted files and the old paths of
+/// renamed files are included, so dependencies that no longer exist and aliases are answered
+/// like git would. Tools that query many files repeatedly can build it once and answer the
+/// queries from it without running git.
+///
+/// The index is only valid for the commit it was built at and the settings the commits depend on,
+/// check it with [`Index::validate`] before using it. Uncommitted changes are not part of it.
+pub struct Index {
+    /// The commit the index was built at
+    pub head: String,
+    /// The root of the repository, the files are relative to it
+    pub root: PathBuf,
+    /// Whose dates are stored, see [`Config::identity`]
+    pub identity: Identity,
+    /// The diff filter of the walk, see [`Config::diff_filter`]
+    pub diff_filter: Option<String>,
+    /// The latest commits of the files, newest first as in the walk
+    commits: Vec<CommitInfo>,
+    /// Each path in the history and the position of its latest commit in `commits`
+    files: BTreeMap<String, usize>,
+}
+
+impl Index {
+    /// Finds the latest commit of every path in the history of the revision of the config (HEAD by
+    /// default) of the repository containing `cwd`
+    pub fn build(config: &Config, cwd: &Path) -> Result<Index, String> {
+        // Absolute, as the listed files are joined to it
+        let cwd = cwd
+            .canonicalize()
+            .map_err(|e| format!("Invalid directory {}: {e}", cwd.display()))?;
+        let repository = config.open_repository(&cwd)?;
+        let head = repository.resolve_commit(config.revision.as_deref().unwrap_or("HEAD"))?;
+        let root = repository.repo_root()?;
+
+        // Only the commits that are the latest of a path are kept, in the order of the walk
+        let mut ranked: Vec<(String, (usize, CommitInfo))> = repository
+            .latest_commits_of_all_paths()?
+            .into_iter()
+            .collect();
+        ranked.sort_by_key(|(_, (rank, _))| *rank);
+        let mut commits: Vec<CommitInfo> = Vec::new();
+        let mut index_files = BTreeMap::new();
+        for (file, (_, commit)) in ranked {
+            if commits.last().is_none_or(|last| last.hash != commit.hash) {
+                commits.push(commit);
+            }
+            index_files.insert(file, commits.len() - 1);
+        }
+
+        Ok(Index {
+            head,
+            root,
+            identity: config.identity,
+            diff_filter: config.diff_filter.clone(),
+            commits,
+            files: index_files,
+        })
+    }
+
+    /// Reads an index written with [`Index::to_toml`]
+    pub fn load(path: &Path) -> Result<Index, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read index {}: {e}", path.display()))?;
+        Index::parse(&content).map_err(|e| format!("Invalid index {}: {e}", path.display()))
+    }
+
+    fn parse(content: &str) -> Result<Index, String> {
+        let table = content.parse::<toml::Table>().map_err(|e| e.to_string())?;
+        if table.get("version").and_then(toml::Value::as_integer) != Some(VERSION) {
+            return Err(format!(
+                "Unsupported version (expected {VERSION}), rebuild the index"
+            ));
+        }
+        let string = |table: &toml::Table, key: &str| -> Result<String, String> {
+            table
+                .get(key)
+                .and_then(toml::Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| format!("Missing string {key}"))
+        };
+
+        let commits = table
+            .get("commits")
+            .and_then(toml::Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|commit| {
+                let commit = commit.as_table().ok_or("Commits must be tables")?;
+                Ok(CommitInfo {
+                    hash: string(commit, "hash")?,
+                    short: string(commit, "short")?,
+                    date: string(commit, "date")?,
+                    timestamp: commit
+                        .get("timestamp")
+                        .and_then(toml::Value::as_integer)
+                        .ok_or("Missing integer timestamp")?,
+                    name: string(commit, "name")?,
+                    email: string(commit, "email")?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let files = match table.get("files") {
+            Some(toml::Value::Table(files)) => files
+                .iter()
+                .map(|(file, position)| {
+                    position
+                        .as_integer()
+                        .and_then(|position| usize::try_from(position).ok())
+                        .filter(|position| *position < commits.len())
+                        .map(|position| (file.clone(), position))
+                        .ok_or_else(|| format!("Invalid commit of {file}"))
+                })
+                .collect::<Result<_, String>>()?,
+            Some(_) => return Err("files must be a table".to_string()),
+            None => BTreeMap::new(),
+        };
+
+        Ok(Index {
+            head: string(&table, "head")?,
+            root: PathBuf::from(string(&table, "root")?),
+            identity: Identity::parse(&string(&table, "identity")?)?,
+            diff_filter: table
+                .get("diff_filter")
+                .and_then(toml::Value::as_str)
+                .map(str::to_string),
+            commits,
+            files,
+        })
+    }
+
+    /// The index as TOML: the settings, the commits in the order of the walk and the position of
+    /// the latest commit of each file
+    pub fn to_toml(&self) -> String {
+        let mut lines = vec![
+            format!("version = {VERSION}"),
+            format!("head = {}", quote(&self.head)),
+            format!("root = {}", quote(&self.root.to_string_lossy())),
+            format!("identity = {}", quote(self.identity.name())),
+        ];
+        if let Some(filter) = &self.diff_filter {
+            lines.push(format!("diff_filter = {}", quote(filter)));
+        }
+        for commit in &self.commits {
+            lines.push(String::new());
+            lines.push("[[commits]]".to_string());
+            lines.push(format!("hash = {}", quote(&commit.hash)));
+            lines.push(format!("short = {}", quote(&commit.short)));
+            lines.push(format!("date = {}", quote(&commit.date)));
+            lines.push(format!("timestamp = {}", commit.timestamp));
+            lines.push(format!("name = {}", quote(&commit.name)));
+            lines.push(format!("email = {}", quote(&commit.email)));
+        }
+        lines.push(String::new());
+        lines.push("[files]".to_string());
+        for (file, position) in &self.files {
+            lines.push(format!("{} = {position}", quote(file)));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Checks that the index answers queries like the repository with the config would: built at
+    /// the commit the revision points to now, in the same repository and with the same settings.
+    /// The error tells why the index is stale.
+    pub fn validate(&self, config: &Config, repository: &dyn VersionControl) -> Result<(), String> {
+        let revision = config.revision.as_deref().unwrap_or("HEAD");
+        let head = repository.resolve_commit(revision)?;
+        if head != self.head {
+            return Err(format!("built at {}, but {revision} is {head}", self.head));
+        }
+        let root = repository.repo_root()?;
+        if root != self.root {
+            return Err(format!(
+                "built for the repository {}, not {}",
+                self.root.display(),
+                root.display()
+            ));
+        }
+        if config.identity != self.identity || config.diff_filter != self.diff_filter {
+            return Err("built with another --identity or --diff-filter".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether the path (absolute or relative to the root) is inside the indexed repository
+    pub fn covers(&self, path: &Path) -> bool {
+        normalize_path(&self.root.join(path)).starts_with(&self.root)
+    }
+
+    /// The latest commit affecting any of the paths (files or directories, absolute or relative
+    /// to the root), without running git. Paths outside the repository and files that were never
+    /// committed have no commit.
+    pub fn latest_commit(&self, paths: &[PathBuf]) -> Option<&CommitInfo> {
+        paths
+            .iter()
+            .filter_map(|path| {
+                let path = normalize_path(&self.root.join(path));
+                let relative = path.strip_prefix(&self.root).ok()?.to_string_lossy();
+                // The file itself and, for a directory, all files inside it
+                self.files
+                    .range(relative.to_string()..)
+                    .take_while(|(file, _)| file.starts_with(relative.as_ref()))
+                    .filter(|(file, _)| {
+                        relative.is_empty()
+                            || file.len() == relative.len()
+                            || file[relative.len()..].starts_with('/')
+                    })
+                    .map(|(_, position)| *position)
+                    .min()
+            })
+            .min()
+            .map(|position| &self.commits[position])
+    }
+}
+
+/// Quotes a string as a TOML basic string
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,11 @@
+//! Finds the latest commit affecting a file and its dependencies, as listed in a `.deps.toml` file.
+//! The command line tool is a thin wrapper around this library.
+
+#[cfg(feature = "auto-deps")]
+pub mod auto_deps;
+pub mod cache;
+pub mod config;
+pub mod git;
+pub mod index;
+pub mod manifest;
+pub mod vcs;
@@ -1,68 +1,319 @@
+mod cli;
+mod output;
+mod sink;
+
+use change_monitor::{
+    index::Index,
+    manifest::{resolve_files, Manifest, ManifestDiff, ManifestFormat, ResolvedFiles},
+    vcs::{normalize_path, CommitInfo, VersionControl},
+};
+use cli::{parse_args, DepsScope, Options, Value, USAGE};
 use log::{self, debug, error, info, warn};
+use output::{json_string, Breakdown, Format, Record};
 use std::{
-    env, fs,
+    env,
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
-    process::{Command, ExitStatus},
+    process::{Command, Stdio},
+    sync::Mutex,
 };
 
-const DEPENDENCIES_PATH: &str = ".deps.toml";
+/// All warnings logged so far, which `--fail-on-warning` turns into an error
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
 
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Logs a warning and records it for `--fail-on-warning`
+fn warning(message: String) {
+    warn!("{message}");
+    WARNINGS.lock().unwrap().push(message);
+}
 
-/// Check if the working tree is clean, i.e., no uncommitted changes
-fn is_working_tree_clean(files: &Vec<String>, cwd: &Path) -> bool {
-    let output = Command::new("git")
-        .current_dir(cwd)
-        .arg("status")
-        .arg("--porcelain=v2") // stable scripting interface
-        .args(files)
-        .output()
-        .unwrap();
+/// With `--fail-on-warning`, reports all warnings so far as errors and exits
+fn check_warnings(options: &Options) {
+    let warnings = WARNINGS.lock().unwrap();
+    if options.fail_on_warning && !warnings.is_empty() {
+        for message in warnings.iter() {
+            error!("{message}");
+        }
+        error!(
+            "{} warning(s) treated as errors (--fail-on-warning)",
+            warnings.len()
+        );
+        std::process::exit(1);
+    }
+}
 
-    // if there is no output, working tree is clean
-    output.stdout.is_empty()
+/// Formats a Unix timestamp as a short UTC date (YYYY-MM-DD), same as git's `%cs`.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn format_epoch_date(epoch: i64) -> String {
+    let z = epoch.div_euclid(86400) + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
 }
 
-/// Checks if inside .git repository.
-/// Theoretically redundant, only for nicer error messages.
-fn check_git_repository(cwd: &Path) -> Result<ExitStatus, String> {
-    let output = Command::new("git")
-        .current_dir(cwd)
-        .arg("rev-parse")
-        .arg("--is-inside-work-tree")
-        .output()
-        .expect("Failed to execute git command");
+/// Abbreviates a hash to exactly `width` characters.
+/// Unlike git's abbreviations, the result is not extended to stay unique.
+fn fixed_width(hash: &str, width: usize) -> Result<String, String> {
+    hash.get(..width)
+        .map(str::to_string)
+        .ok_or_else(|| format!("Hash {hash} is shorter than the requested width of {width}"))
+}
 
-    if output.status.success() {
-        Ok(output.status)
-    } else {
-        Err("Not a git repository (or any of the parent directories): .git".to_string())
+/// Like `fixed_width`, but a width longer than the hash is a usage error that ends the program
+fn fixed_width_or_exit(hash: &str, width: usize) -> String {
+    fixed_width(hash, width).unwrap_or_else(|e| {
+        error!("{e}");
+        std::process::exit(1);
+    })
+}
+
+/// Reads the date from the `SOURCE_DATE_EPOCH` environment variable, if set.
+/// An invalid value is an error that ends the program.
+/// See https://reproducible-builds.org/specs/source-date-epoch/
+fn source_date_epoch() -> Option<String> {
+    let value = env::var("SOURCE_DATE_EPOCH").ok()?;
+    let epoch = value.trim().parse::<i64>().unwrap_or_else(|_| {
+        error!("Invalid SOURCE_DATE_EPOCH: {value} (expected the seconds since 1970-01-01)");
+        std::process::exit(1);
+    });
+    Some(format_epoch_date(epoch))
+}
+
+/// Dumps the resolved files of every entry of the dependencies file as JSON.
+/// The queried file is included even if it has no entry.
+fn dump_resolved(target: &Target) -> String {
+    let manifest = target.manifest.as_ref();
+    let filename = target.filename.as_str();
+    let mut keys: Vec<&str> = manifest
+        .map(|manifest| {
+            manifest
+                .entries
+                .iter()
+                .map(|entry| entry.key.as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+    if !keys.contains(&filename) {
+        keys.push(filename);
+    }
+
+    let entries: Vec<String> = keys
+        .into_iter()
+        .map(|key| {
+            let resolved = if key == filename {
+                &target.resolved
+            } else {
+                &resolve_files(manifest, key, target.base_directory_string())
+            };
+            let files: Vec<String> = resolved.files.iter().map(|f| json_string(f)).collect();
+            let aliases: Vec<String> = resolved.aliases.iter().map(|f| json_string(f)).collect();
+            format!(
+                "{}: {{ \"files\": [{}], \"aliases\": [{}], \"from_fallback\": {} }}",
+                json_string(key),
+                files.join(", "),
+                aliases.join(", "),
+                resolved.from_fallback
+            )
+        })
+        .collect();
+    format!("{{ {} }}", entries.join(", "))
+}
+
+/// Loads a dependencies file for `--compare-manifests`, which fails if it doesn't exist
+fn load_manifest(path: &str) -> Manifest {
+    let manifest = Manifest::load(Path::new(path)).unwrap_or_else(|e| {
+        error!("Failed to parse {path}: {e}");
+        std::process::exit(1);
+    });
+    manifest.unwrap_or_else(|| {
+        error!("{path} does not exist");
+        std::process::exit(1);
+    })
+}
+
+/// Prints the differences between two dependencies files, one line per added (`+`), removed (`-`)
+/// or changed (`~`) entry, followed by the changed dependencies and aliases of the entry.
+fn compare_manifests(diff: &ManifestDiff, json: bool) -> String {
+    if json {
+        let list = |values: &[String]| -> String {
+            let values: Vec<String> = values.iter().map(|value| json_string(value)).collect();
+            format!("[{}]", values.join(", "))
+        };
+        let changed: Vec<String> = diff
+            .changed
+            .iter()
+            .map(|change| {
+                format!(
+                    "{{ \"key\": {}, \"added_dependencies\": {}, \"removed_dependencies\": {}, \"added_aliases\": {}, \"removed_aliases\": {}, \"dirty\": {}, \"fallback\": {} }}",
+                    json_string(&change.key),
+                    list(&change.added_dependencies),
+                    list(&change.removed_dependencies),
+                    list(&change.added_aliases),
+                    list(&change.removed_aliases),
+                    change
+                        .dirty
+                        .map_or("null".to_string(), |dirty| dirty.to_string()),
+                    change
+                        .fallback
+                        .map_or("null".to_string(), |fallback| fallback.to_string())
+                )
+            })
+            .collect();
+        return format!(
+            "{{ \"added\": {}, \"removed\": {}, \"changed\": [{}] }}",
+            list(&diff.added),
+            list(&diff.removed),
+            changed.join(", ")
+        );
+    }
+
+    let mut lines = Vec::new();
+    lines.extend(diff.added.iter().map(|key| format!("+ {key}")));
+    lines.extend(diff.removed.iter().map(|key| format!("- {key}")));
+    for change in &diff.changed {
+        lines.push(format!("~ {}", change.key));
+        let details = [
+            ('+', "dependency", &change.added_dependencies),
+            ('-', "dependency", &change.removed_dependencies),
+            ('+', "alias", &change.added_aliases),
+            ('-', "alias", &change.removed_aliases),
+        ];
+        for (sign, kind, values) in details {
+            lines.extend(
+                values
+                    .iter()
+                    .map(|value| format!("  {sign} {kind} {value}")),
+            );
+        }
+        if let Some(dirty) = change.dirty {
+            lines.push(format!("  ~ dirty = {dirty}"));
+        }
+        if let Some(fallback) = change.fallback {
+            lines.push(format!("  ~ fallback = {fallback}"));
+        }
     }
+    lines.join("\n")
 }
 
-/// Finds the latest commit affecting the files, or the date of this latest commit
-fn get_latest_commit(files: &Vec<String>, get_date: bool, cwd: &Path) -> Option<String> {
-    let format = if get_date {
-        "--pretty=format:%cs"
-    } else {
-        "--pretty=format:%H"
-    }; // cs is commiter date, short format: https://git-scm.com/docs/pretty-formats
-    let output = Command::new("git")
-        .current_dir(cwd)
-        .arg("log")
-        .arg("-1")
-        .arg(format)
-        .arg("--")
-        .args(files)
-        .output()
-        .expect("Failed to execute git command");
+/// Records the state of every monitored file as JSON: its path relative to the repository root,
+/// the latest commit affecting it and the blob hashes of its current content and at HEAD.
+fn provenance(target: &Target, options: &Options) -> String {
+    let repository = &target.repository;
+    let root = repository
+        .repo_root()
+        .unwrap_or_else(|e| panic!("Failed to find repository root: {e}"));
+    let paths = repository
+        .list_files(&target.resolved.files)
+        .unwrap_or_else(|e| panic!("Failed to list monitored files: {e}"));
 
-    if output.status.success() {
-        let commit_hash = String::from_utf8_lossy(&output.stdout);
-        // Return as string but map empty string to None
-        Some(commit_hash.to_string()).filter(|s| !s.is_empty())
-    } else {
-        None
+    let optional = |value: Option<String>| value.map_or("null".to_string(), |v| json_string(&v));
+    let files: Vec<String> = paths
+        .iter()
+        .map(|absolute| {
+            let path = absolute
+                .strip_prefix(&root)
+                .unwrap_or(absolute)
+                .to_string_lossy()
+                .into_owned();
+            let commit = repository
+                .latest_commit(&[absolute.to_string_lossy().into_owned()])
+                .map(|commit| commit.hash);
+            let blob = repository.content_blob(&path);
+            let committed_blob = repository.committed_blob(&path);
+            format!(
+                "{{ \"path\": {}, \"commit\": {}, \"blob\": {}, \"committed_blob\": {}, \"dirty\": {} }}",
+                json_string(&path),
+                optional(commit),
+                optional(blob.clone()),
+                optional(committed_blob.clone()),
+                blob != committed_blob
+            )
+        })
+        .collect();
+
+    let commit = repository
+        .latest_commit(&target.commit_files(options))
+        .map(|commit| commit.hash);
+    format!(
+        "{{ \"file\": {}, \"commit\": {}, \"files\": [{}] }}",
+        json_string(&target.argument),
+        optional(commit),
+        files.join(", ")
+    )
+}
+
+/// Opens the version control backend for the directory.
+/// If no backend is selected, it is auto-detected (currently only git is supported).
+fn open_backend(options: &Options, cwd: &Path) -> Result<Box<dyn VersionControl>, String> {
+    match options.vcs.as_deref() {
+        None | Some("git") => Ok(Box::new(options.config().open_repository(cwd)?)),
+        Some(other) => Err(format!("Unsupported version control system: {other}")),
+    }
+}
+
+/// A file given on the command line, together with everything needed to query it
+struct Target {
+    /// The file as given on the command line
+    argument: String,
+    base_directory: PathBuf,
+    filename: String,
+    repository: Box<dyn VersionControl>,
+    manifest: Option<Manifest>,
+    /// All files that shall be monitored
+    resolved: ResolvedFiles,
+    /// The pathspecs of the file itself, without its dependencies
+    primary: Vec<String>,
+}
+
+impl Target {
+    /// The files used for the commit query, including aliases
+    fn commit_files(&self, options: &Options) -> Vec<String> {
+        match options.commit_deps {
+            DepsScope::All => self.resolved.history_files(),
+            DepsScope::File => self.file_history_files(),
+        }
+    }
+
+    /// The file itself and its aliases, without the dependencies
+    fn file_history_files(&self) -> Vec<String> {
+        self.primary
+            .iter()
+            .chain(&self.resolved.aliases)
+            .cloned()
+            .collect()
+    }
+
+    /// The monitored files that are not the file itself
+    fn dependency_files(&self) -> Vec<String> {
+        self.resolved
+            .files
+            .iter()
+            .filter(|file| !self.primary.contains(file))
+            .cloned()
+            .collect()
+    }
+
+    /// The files used for the dirty check, None if the entry disables it with `dirty = false`
+    fn dirty_check_files(&self, options: &Options) -> Option<&[String]> {
+        if !self.resolved.dirty_check {
+            return None;
+        }
+        match options.dirty_deps {
+            DepsScope::All => Some(&self.resolved.files),
+            DepsScope::File => Some(&self.primary),
+        }
+    }
+
+    fn base_directory_string(&self) -> &str {
+        self.base_directory
+            .to_str()
+            .expect("Cannot convert base directory to string")
     }
 }
 
@@ -72,27 +323,15 @@ fn get_latest_commit(files: &Vec<String>, get_date: bool, cwd: &Path) -> Option<
 /// (and all subdirectories) are used for the git log command.
 /// If the file is not yet commited, the complete local directory (and all subdirectories) are used for the
 /// git log command.
-fn main() {
-    simple_logger::init().unwrap();
-
-    let args: Vec<String> = env::args().collect();
-
-    // Accept only 2 or 3 (with --date) arguments
-    if args.len() < 2 || args.len() > 3 {
-        eprintln!("Usage: {} <filename> [--date]\n--date outputs only the date of the latest commit instead of the hash", args[0]);
-        std::process::exit(1);
-    }
-
-    // Check for version flag
-    if &args[1] == "-v" || &args[1] == "--version" {
-        eprintln!("Version: {}", VERSION);
-        std::process::exit(0);
+fn open_target(argument: &str, options: &Options) -> Target {
+    if options.glob || (is_glob(argument) && !Path::new(argument).exists()) {
+        return open_glob_target(argument, options);
     }
 
     // Extract the file to be monitored
-    let filepath = PathBuf::from(&args[1])
+    let filepath = PathBuf::from(argument)
         .canonicalize()
-        .unwrap_or_else(|e| panic!("Invalid file: {}. Error: {}", &args[1], e));
+        .unwrap_or_else(|e| panic!("Invalid file: {}. Error: {}", argument, e));
 
     // Check if the file exists at all
     filepath
@@ -115,8 +354,8 @@ fn main() {
 
     debug!("Using base_directory: {:#?}", base_directory);
 
-    // Ensure that there is a git repository present.
-    check_git_repository(base_directory).expect("Checking git repository failed");
+    // Ensure that there is a repository present.
+    let repository = open_backend(options, base_directory).expect("Checking repository failed");
 
     // Extract the filename from the path for later use
     let filename = filepath
@@ -127,80 +366,1116 @@ fn main() {
 
     info!("Monitor changes for file: {:#?}", filepath);
 
-    // Check if `--date` argument was passed
-    let get_date = args.get(2).map_or(false, |arg| arg == "--date");
-
     // Construct path where dependencies TOML file should be
-    let dependencies_path = base_directory.join(DEPENDENCIES_PATH);
+    let dependencies_path = options.config().dependencies_path(base_directory);
+    select_manifest_format(base_directory, &dependencies_path, options);
 
     // If the TOML exists, use it, otherwise set to None.
-    let dependencies = if dependencies_path.exists() {
-        // Parse toml into table
-        let toml_file_string =
-            fs::read_to_string(&dependencies_path).expect("Failed to read .deps.toml");
-        let toml_file_table: toml::map::Map<String, toml::Value> = toml_file_string
-            .parse::<toml::Table>()
-            .expect("Failed to parse .deps.toml");
-
-        // Get the "dependencies" key as an array and convert to string
-        let dependencies: Option<Vec<String>> = toml_file_table
-            .get(filename)
-            .and_then(|key| key.get("dependencies"))
-            .and_then(|deps| deps.as_array())
-            .map(|deps| {
-                deps.iter()
-                    .map(|dep| {
-                        dep.as_str()
-                            .expect("dependency was not a string")
-                            .to_string()
-                    })
-                    .collect()
-            });
-        dependencies
+    let manifest = Manifest::load(&dependencies_path).unwrap_or_else(|e| panic!("{e}"));
+    if manifest.is_none() && options.require_manifest && options.files_command.is_none() {
+        error!(
+            "No dependencies file found for {argument}: {}",
+            dependencies_path.display()
+        );
+        std::process::exit(1);
+    }
+
+    // A directory is monitored as a whole
+    let primary = if filepath.is_dir() {
+        base_directory_string.to_string()
     } else {
-        None
+        filename.to_string()
+    };
+
+    // Collect a Vec of all files that shall be monitored, listed by the command if given.
+    let resolved = match &options.files_command {
+        Some(command) => {
+            let mut files = vec![primary.clone()];
+            files.extend(
+                run_files_command(command, base_directory).unwrap_or_else(|e| {
+                    error!("{e}");
+                    std::process::exit(1);
+                }),
+            );
+            ResolvedFiles {
+                files,
+                aliases: Vec::new(),
+                from_fallback: false,
+                dirty_check: true,
+            }
+        }
+        None => options
+            .config()
+            .resolve(manifest.as_ref(), filename, base_directory_string),
+    };
+
+    // Without an entry, the dependencies can be derived from the imports of the file instead
+    #[cfg(feature = "auto-deps")]
+    let resolved = match options.auto_deps {
+        Some(language) if resolved.from_fallback && !filepath.is_dir() => {
+            let mut files = vec![primary.clone()];
+            files.extend(change_monitor::auto_deps::dependencies(
+                language,
+                base_directory,
+                filename,
+            ));
+            ResolvedFiles {
+                files,
+                aliases: Vec::new(),
+                from_fallback: false,
+                dirty_check: resolved.dirty_check,
+            }
+        }
+        _ => resolved,
+    };
+
+    // The fallback directory is limited to the files up to the given depth
+    let resolved = match options.max_depth {
+        Some(depth) if resolved.from_fallback => ResolvedFiles {
+            files: depth_pathspecs(base_directory_string, depth),
+            ..resolved
+        },
+        _ => resolved,
     };
 
     debug!(
         "Searching: {:#?}. Found dependencies: {:#?}",
-        dependencies_path, dependencies,
+        dependencies_path, resolved.files,
     );
 
-    // Collect a Vec of all files that shall be monitored.
-    // First, determine whether any dependencies for the file are specified.
-    // This is nested in one extra struct so we can extend this later on without breaking the existing toml files.
-    let all_files = match dependencies {
-        Some(deps) => {
-            let mut files = vec![filename.to_string()]; // Always include the filename itself
-            files.extend(deps.into_iter().map(|dep| dep.to_string()));
+    if resolved.from_fallback && !options.dump_resolved {
+        // If the given filename hasn't been specified in the toml file, we just we watch the file's base_directory.
+        warning(format!(
+            "No dependencies entry found for file {:#?}. Monitoring basedirectory.",
+            filename
+        ));
+    }
+
+    debug!("Files monitored for changes: {:#?}", resolved.files);
+
+    Target {
+        argument: argument.to_string(),
+        base_directory: base_directory.to_path_buf(),
+        filename: filename.to_string(),
+        repository,
+        manifest,
+        resolved,
+        primary: vec![primary],
+    }
+}
+
+/// Runs the `--files-command` with `sh -c` in the base directory and returns the paths it prints,
+/// NUL separated if the output contains a NUL, one per line otherwise
+fn run_files_command(command: &str, base_directory: &Path) -> Result<Vec<String>, String> {
+    debug!("Running files command: {command}");
+    let output = Command::new("sh")
+        .current_dir(base_directory)
+        .arg("-c")
+        .arg(command)
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("Failed to run files command: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Files command failed ({}): {command}",
+            output.status
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let separator = if stdout.contains('\0') { '\0' } else { '\n' };
+    let files: Vec<String> = stdout
+        .split(separator)
+        .map(|path| path.strip_suffix('\r').unwrap_or(path))
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect();
+    if files.is_empty() {
+        return Err(format!("Files command printed no paths: {command}"));
+    }
+    Ok(files)
+}
+
+const GLOB_METACHARACTERS: [char; 3] = ['*', '?', '['];
+
+/// Whether the argument contains glob metacharacters
+fn is_glob(argument: &str) -> bool {
+    argument.contains(GLOB_METACHARACTERS)
+}
+
+/// Whether git interprets the pathspec instead of taking it as a literal path, i.e. it contains
+/// wildcards or pathspec magic like `:(exclude)`, so it can't be compared to paths in-process
+fn is_pathspec_magic(pathspec: &str) -> bool {
+    is_glob(pathspec) || pathspec.starts_with(':')
+}
+
+/// Checks which dependencies files exist in the directory. If there are several, the first by
+/// `ManifestFormat::PRECEDENCE` is used and the others are reported, as the filesystem order
+/// would be arbitrary. Only the TOML format can be read yet, so it can't be overridden either.
+fn select_manifest_format(base_directory: &Path, toml_path: &Path, options: &Options) {
+    // An explicit --deps-format selects the file without a warning
+    if options.deps_format.is_some() {
+        return;
+    }
+    let found: Vec<(ManifestFormat, PathBuf)> = ManifestFormat::PRECEDENCE
+        .into_iter()
+        .flat_map(|format| {
+            let paths: Vec<PathBuf> = match format {
+                ManifestFormat::Toml => vec![toml_path.to_path_buf()],
+                _ => format
+                    .file_names()
+                    .iter()
+                    .map(|name| base_directory.join(name))
+                    .collect(),
+            };
+            paths.into_iter().map(move |path| (format, path))
+        })
+        .filter(|(_, path)| path.exists())
+        .collect();
+
+    let Some(((format, path), others)) = found.split_first() else {
+        return;
+    };
+    if *format != ManifestFormat::Toml {
+        warning(format!(
+            "{} is not supported yet and ignored, only .deps.toml can be read",
+            path.display()
+        ));
+    } else if !others.is_empty() {
+        let ignored: Vec<String> = others
+            .iter()
+            .map(|(_, other)| other.display().to_string())
+            .collect();
+        warning(format!(
+            "Multiple dependencies files found, using {} and ignoring {}. Use --deps-format toml to confirm.",
+            path.display(),
+            ignored.join(", ")
+        ));
+    }
+}
+
+/// Pathspecs matching the files in the directory and its subdirectories up to the depth, i.e., with
+/// at most `depth` path components below the directory (`*`, `*/*`, ...). As `:(glob)` pathspecs,
+/// `*` doesn't match `/`, and untracked files are matched as well for the dirty check.
+fn depth_pathspecs(directory: &str, depth: usize) -> Vec<String> {
+    let mut escaped = String::new();
+    for c in directory.chars() {
+        if matches!(c, '*' | '?' | '[' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    (1..=depth)
+        .map(|depth| format!(":(glob){escaped}/{}", vec!["*"; depth].join("/")))
+        .collect()
+}
+
+/// Monitors the files matching a glob pattern as one group, without a dependencies file.
+/// The pattern is passed to git as `:(glob)` pathspec, relative to its leading directories without
+/// metacharacters, which serve as base directory.
+fn open_glob_target(argument: &str, options: &Options) -> Target {
+    // Split at the last separator before the first metacharacter
+    let first_metacharacter = argument.find(GLOB_METACHARACTERS).unwrap_or(argument.len());
+    let (directory, pattern) = match argument[..first_metacharacter].rfind('/') {
+        Some(0) => ("/", &argument[1..]),
+        Some(separator) => (&argument[..separator], &argument[separator + 1..]),
+        None => (".", argument),
+    };
+
+    let base_directory = PathBuf::from(directory).canonicalize().unwrap_or_else(|e| {
+        panic!("Invalid directory of glob {argument}: {directory}. Error: {e}")
+    });
+    debug!("Using base_directory: {:#?}", base_directory);
+
+    let repository = open_backend(options, &base_directory).expect("Checking repository failed");
+
+    info!("Monitor changes for files matching: {:#?}", argument);
+
+    // The existence check applies to the expanded glob
+    let pathspec = format!(":(glob){pattern}");
+    let matches = repository
+        .list_files(std::slice::from_ref(&pathspec))
+        .unwrap_or_else(|e| {
+            error!("Failed to expand glob {argument}: {e}");
+            std::process::exit(1);
+        });
+    if matches.is_empty() {
+        error!("No tracked files match {argument}");
+        std::process::exit(1);
+    }
+    debug!("Files matching {argument}: {:#?}", matches);
+
+    Target {
+        argument: argument.to_string(),
+        base_directory,
+        filename: argument.to_string(),
+        repository,
+        manifest: None,
+        resolved: ResolvedFiles {
+            files: vec![pathspec.clone()],
+            aliases: Vec::new(),
+            from_fallback: false,
+            dirty_check: true,
+        },
+        primary: vec![pathspec],
+    }
+}
+
+/// The tool assumes that all monitored files are in one repository.
+/// Warns if the targets span several repositories, or fails if `single_repo` is set.
+fn check_single_repository(targets: &[Target], single_repo: bool) -> Result<Vec<PathBuf>, String> {
+    let mut target_roots = Vec::new();
+    let mut repo_roots: Vec<(PathBuf, &str)> = Vec::new();
+    for target in targets {
+        let repo_root = target.repository.repo_root()?;
+        debug!("Using repository {:#?} for {}", repo_root, target.argument);
+        if !repo_roots.iter().any(|(root, _)| *root == repo_root) {
+            repo_roots.push((repo_root.clone(), &target.argument));
+        }
+        target_roots.push(repo_root);
+    }
+
+    if repo_roots.len() > 1 {
+        let roots: Vec<String> = repo_roots
+            .iter()
+            .map(|(root, argument)| format!("{} (e.g. {argument})", root.display()))
+            .collect();
+        let message = format!(
+            "Monitored files span multiple repositories: {}",
+            roots.join(", ")
+        );
+        if single_repo {
+            return Err(message);
+        }
+        warning(message);
+    }
+    Ok(target_roots)
+}
+
+/// Warns about the monitored files that are ignored by git, as they have no history and silently
+/// don't contribute to the commit. The files of all targets in a repository are checked with one
+/// git command, by their absolute paths, and reported relative to the repository root.
+fn warn_ignored_files(targets: &[Target], roots: &[PathBuf]) {
+    let mut checked: Vec<&PathBuf> = Vec::new();
+    for (target, root) in targets.iter().zip(roots) {
+        if checked.contains(&root) {
+            continue;
+        }
+        checked.push(root);
+        let mut files: Vec<String> = Vec::new();
+        for (other, _) in targets
+            .iter()
+            .zip(roots)
+            .filter(|(_, other)| *other == root)
+        {
+            // `git check-ignore` takes the paths literally
+            for file in other
+                .resolved
+                .files
+                .iter()
+                .filter(|file| !is_pathspec_magic(file))
+            {
+                let path = normalize_path(&other.base_directory.join(file));
+                let path = path.to_string_lossy().into_owned();
+                if !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+        }
+        for file in target.repository.ignored_files(&files) {
+            let file = Path::new(&file)
+                .strip_prefix(root)
+                .unwrap_or(Path::new(&file));
+            warning(format!(
+                "Monitored file {:?} is ignored by git, so it doesn't affect the result. \
+                 Use --fingerprint --include-ignored to hash its content.",
+                file.display()
+            ));
+        }
+    }
+}
+
+/// For `--by-content`: the latest commit that introduced the current content of any of the
+/// files, under any path and on any branch. Content that was never committed is skipped.
+fn latest_content_commit(target: &Target, files: &[String]) -> Option<CommitInfo> {
+    let repository = &target.repository;
+    let root = repository
+        .repo_root()
+        .unwrap_or_else(|e| panic!("Failed to find repository root: {e}"));
+    let paths = repository
+        .list_files(files)
+        .unwrap_or_else(|e| panic!("Failed to list monitored files: {e}"));
+
+    paths
+        .iter()
+        .filter_map(|absolute| {
+            let path = absolute.strip_prefix(&root).unwrap_or(absolute);
+            let blob = repository.content_blob(&path.to_string_lossy())?;
+            let commit = repository.latest_commit_with_blob(&blob);
+            debug!(
+                "Content {blob} of {path:?} introduced by {:?}",
+                commit.as_ref().map(|commit| &commit.hash)
+            );
+            commit
+        })
+        .max_by_key(|commit| commit.timestamp)
+}
+
+/// Whether the output depends on the dirty state, otherwise `git status` is skipped
+fn needs_dirty_check(options: &Options) -> bool {
+    if options.no_dirty_check {
+        return false;
+    }
+    // SOURCE_DATE_EPOCH is only used for a clean working tree
+    let source_date_epoch =
+        options.respect_source_date_epoch && env::var_os("SOURCE_DATE_EPOCH").is_some();
+    if options.verify_clean_after
+        || options.json_out.is_some()
+        || options.env_out.is_some()
+        || source_date_epoch
+    {
+        return true;
+    }
+    match &options.template {
+        Some(template) => template.uses("dirty"),
+        None => options.format.needs_dirty_check(options.value),
+    }
+}
+
+/// Finds the latest commit affecting the monitored files of the target
+fn latest_commit(target: &Target, options: &Options) -> Option<CommitInfo> {
+    let history_files = target.commit_files(options);
+    if options.by_content {
+        latest_content_commit(target, &history_files)
+    } else {
+        target.repository.latest_commit(&history_files)
+    }
+}
+
+/// Finds the latest commits of all targets in a single walk of the history instead of one query
+/// per target. Only possible if they are in the same repository and only use literal paths.
+fn batched_latest_commits(
+    targets: &[Target],
+    options: &Options,
+) -> Option<Vec<Option<CommitInfo>>> {
+    if targets.len() < 2 || options.by_content {
+        return None;
+    }
+    let root = targets[0].repository.repo_root().ok()?;
+    let mut groups = Vec::new();
+    for target in targets {
+        let files = target.commit_files(options);
+        // Wildcards and pathspec magic (e.g. of glob patterns) can't be matched to the changed files
+        if target.repository.repo_root().ok()? != root
+            || files.iter().any(|file| is_pathspec_magic(file))
+        {
+            return None;
+        }
+        groups.push(
             files
+                .iter()
+                .map(|file| target.base_directory.join(file))
+                .collect(),
+        );
+    }
+    debug!(
+        "Querying the latest commits of {} files in one walk",
+        targets.len()
+    );
+    // A path git rejects fails the whole walk, so the targets are queried one by one instead
+    targets[0]
+        .repository
+        .latest_commits(&groups)
+        .map_err(|e| debug!("Querying the files one by one, the walk failed: {e}"))
+        .ok()
+}
+
+/// Writes the latest commit of every tracked file of the repository in the current directory
+/// to the index file
+fn build_index(path: &str, options: &Options) -> Result<(), String> {
+    let index = Index::build(&options.config(), Path::new("."))?;
+    let content = index.to_toml();
+    if options.dry_run {
+        info!(
+            "Dry run: would write the index ({} bytes) to {path}",
+            content.len()
+        );
+        return Ok(());
+    }
+    let mut transaction = sink::Transaction::default();
+    transaction.write(Path::new(path), content.as_bytes())?;
+    transaction.commit()
+}
+
+/// Reads the index of `--use-index`, None (with a warning) if it is stale, so git is queried instead
+fn load_index(path: &str, targets: &[Target], options: &Options) -> Option<Index> {
+    let index = Index::load(Path::new(path)).unwrap_or_else(|e| {
+        error!("{e}");
+        std::process::exit(1);
+    });
+    match index.validate(&options.config(), targets[0].repository.as_ref()) {
+        Ok(()) => Some(index),
+        Err(reason) => {
+            warning(format!(
+                "The index {path} is stale ({reason}), querying git instead. Rebuild it with --build-index."
+            ));
+            None
+        }
+    }
+}
+
+/// Looks up the latest commit of the target in the index, None if the index can't answer it:
+/// for wildcards and pathspec magic, or paths outside of the indexed repository
+fn indexed_commit(target: &Target, options: &Options, index: &Index) -> Option<Option<CommitInfo>> {
+    let files = target.commit_files(options);
+    if files.iter().any(|file| is_pathspec_magic(file)) {
+        return None;
+    }
+    let paths: Vec<PathBuf> = files
+        .iter()
+        .map(|file| target.base_directory.join(file))
+        .collect();
+    if !paths.iter().all(|path| index.covers(path)) {
+        return None;
+    }
+    let commit = index.latest_commit(&paths).cloned();
+    debug!(
+        "Latest commit of {} from the index: {:?}",
+        target.argument,
+        commit.as_ref().map(|commit| &commit.hash)
+    );
+    Some(commit)
+}
+
+/// Checks a target for uncommitted changes, None if the check is not needed or disabled
+fn dirty_state(target: &Target, options: &Options) -> Option<bool> {
+    if !needs_dirty_check(options) {
+        return None;
+    }
+    let files = target.dirty_check_files(options)?;
+    Some(!target.repository.is_clean(files))
+}
+
+/// Checks all targets for uncommitted changes with a single query for the union of their files,
+/// instead of one per target. Only possible if they are in the same repository and only use
+/// literal paths, like `batched_latest_commits`.
+fn batched_dirty_states(targets: &[Target], options: &Options) -> Option<Vec<Option<bool>>> {
+    if targets.len() < 2 || !needs_dirty_check(options) {
+        return None;
+    }
+    let root = targets[0].repository.repo_root().ok()?;
+    let mut groups = Vec::new();
+    for target in targets {
+        if target.repository.repo_root().ok()? != root {
+            return None;
+        }
+        // Targets with `dirty = false` are not checked
+        let Some(files) = target.dirty_check_files(options) else {
+            continue;
+        };
+        if files.iter().any(|file| is_pathspec_magic(file)) {
+            return None;
+        }
+        let paths: Vec<PathBuf> = files
+            .iter()
+            .map(|file| target.base_directory.join(file))
+            .collect();
+        groups.push(paths);
+    }
+    debug!(
+        "Checking {} files for uncommitted changes at once",
+        groups.len()
+    );
+    let mut dirty = targets[0]
+        .repository
+        .dirty_groups(&groups)
+        .map_err(|e| debug!("Checking the files one by one, the batched check failed: {e}"))
+        .ok()?
+        .into_iter();
+    Some(
+        targets
+            .iter()
+            .map(|target| target.dirty_check_files(options).and_then(|_| dirty.next()))
+            .collect(),
+    )
+}
+
+/// Computes the record for a target from its latest commit, None if no commits were found
+fn report(
+    target: &Target,
+    options: &Options,
+    commit: Option<CommitInfo>,
+    dirty: Option<bool>,
+) -> Option<Record> {
+    let all_files = &target.resolved.files;
+    let repository = &target.repository;
+    let history_files = target.commit_files(options);
+    let mut commit = commit?;
+    debug!(
+        "Latest commit affecting {:#?}: {}",
+        history_files, commit.hash
+    );
+
+    // The working tree is compared to HEAD, so it can be clean although the reported commit is outdated
+    if let Some(revision) = &options.revision {
+        if let Some(head) = repository.latest_commit_at(&history_files, "HEAD") {
+            if head.hash != commit.hash {
+                warning(format!(
+                    "The latest commit of {} is {} at {revision}, but {} at HEAD",
+                    target.argument, commit.hash, head.hash
+                ));
+            }
         }
-        None => {
-            // If the given filename hasn't been specified in the toml file, we just we watch the file's base_directory.
-            warn!(
-                "No dependencies entry found for file {:#?}. Monitoring basedirectory.",
-                filename
+    }
+
+    // The abbreviated hash gets the fixed width as well
+    let abbreviate = |hash: &str| match options.hash_width {
+        Some(width) => fixed_width_or_exit(hash, width),
+        None => hash.to_string(),
+    };
+    if options.hash_width.is_some() {
+        commit.short = abbreviate(&commit.hash);
+    }
+    let hash = abbreviate(&commit.hash);
+
+    // SOURCE_DATE_EPOCH takes precedence over the commit date, if requested and the monitored
+    // files have no uncommitted changes (or are not checked)
+    let date = (options.respect_source_date_epoch && dirty != Some(true))
+        .then(source_date_epoch)
+        .flatten()
+        .unwrap_or_else(|| commit.date.clone());
+
+    let value = match options.value {
+        Value::Date => date.clone(),
+        Value::TreeHash => abbreviate(&repository.tree_hash(all_files).unwrap_or_else(|e| {
+            error!(
+                "Failed to compute the tree hash of {}: {e}",
+                target.argument
             );
-            vec![base_directory_string.to_string()]
+            std::process::exit(1);
+        })),
+        Value::Fingerprint => abbreviate(
+            &repository
+                .fingerprint(all_files, options.staged)
+                .unwrap_or_else(|e| {
+                    error!(
+                        "Failed to compute the fingerprint of {}: {e}",
+                        target.argument
+                    );
+                    std::process::exit(1);
+                }),
+        ),
+        Value::AgeSeconds => match options.age_format {
+            Some(unit) => unit.apply(commit.age_seconds(), options.number_format),
+            None => options.number_format.apply(commit.age_seconds()),
+        },
+        Value::Parent => match repository.first_parent(&commit.hash) {
+            Ok(Some(parent)) => abbreviate(&parent),
+            Ok(None) => match &options.empty_value {
+                Some(placeholder) => placeholder.clone(),
+                None => {
+                    error!(
+                        "The latest commit {} of {} is a root commit without parent, use --empty-value for a placeholder",
+                        commit.hash, target.argument
+                    );
+                    std::process::exit(1);
+                }
+            },
+            Err(e) => {
+                error!("Failed to get the parent of {}: {e}", commit.hash);
+                std::process::exit(1);
+            }
+        },
+        Value::MergeBase => {
+            let reference = options.merge_base.as_deref().unwrap();
+            match repository.merge_base(&commit.hash, reference) {
+                Ok(Some(base)) => abbreviate(&base),
+                Ok(None) => {
+                    error!(
+                        "No common ancestor of {} and {reference} for {}",
+                        commit.hash, target.argument
+                    );
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    error!("Failed to compute the merge base with {reference}: {e}");
+                    std::process::exit(1);
+                }
+            }
         }
+        Value::Hash => hash.clone(),
     };
 
-    debug!("Files monitored for changes: {:#?}", all_files);
+    let is_dirty = || {
+        target
+            .dirty_check_files(options)
+            .map(|files| !repository.is_clean(files))
+    };
+    let url = options
+        .commit_url_template
+        .as_ref()
+        .map(|template| commit.url(template));
+
+    let diffstat = options.with_diffstat.then(|| {
+        repository
+            .diffstat(&commit.hash, &history_files)
+            .unwrap_or_else(|e| panic!("Failed to compute the diffstat of {}: {e}", commit.hash))
+    });
+
+    let branch = options.with_branch.as_ref().map(|detached| {
+        repository
+            .current_branch()
+            .unwrap_or_else(|e| panic!("Failed to get the current branch: {e}"))
+            .unwrap_or_else(|| detached.clone())
+    });
+
+    // An empty list of paths would match the complete history
+    let breakdown = options.breakdown.then(|| {
+        let latest = |files: Vec<String>| {
+            (!files.is_empty())
+                .then(|| repository.latest_commit(&files))
+                .flatten()
+                .map(|commit| abbreviate(&commit.hash))
+        };
+        Breakdown {
+            file: latest(target.file_history_files()),
+            dependencies: latest(target.dependency_files()),
+        }
+    });
+
+    // Both queries again, if either result changed, files were changed or committed meanwhile
+    if options.verify_clean_after {
+        let commit_after = latest_commit(target, options).map(|commit| commit.hash);
+        let dirty_after = is_dirty();
+        if commit_after.as_ref() != Some(&commit.hash) || dirty != dirty_after {
+            error!(
+                "The monitored files of {} changed while running (commit {} {}, now {} {}), run again",
+                target.argument,
+                commit.hash,
+                dirty_label(dirty),
+                commit_after.as_deref().unwrap_or("none"),
+                dirty_label(dirty_after)
+            );
+            std::process::exit(1);
+        }
+    }
 
-    // Get the latest commit id for all monitored files.
-    let latest_commit = get_latest_commit(&all_files, get_date, base_directory);
+    Some(Record {
+        file: target.argument.clone(),
+        commit,
+        hash,
+        date,
+        value,
+        dirty,
+        url,
+        diffstat,
+        branch,
+        breakdown,
+    })
+}
 
-    // Print the correct commit hash, if any were found. Use println to print to stdout instead of stderr (logging)
-    if let Some(mut commit_hash) = latest_commit {
-        debug!("Latest commit affecting {:#?}: {}", all_files, commit_hash);
+fn dirty_label(dirty: Option<bool>) -> &'static str {
+    match dirty {
+        Some(true) => "dirty",
+        Some(false) => "clean",
+        None => "not checked",
+    }
+}
 
-        // If no date is specified and the working tree is dirty, append a "DIRTY" string
-        if !get_date && !is_working_tree_clean(&all_files, base_directory) {
-            commit_hash.push_str(" DIRTY")
+/// One commit of `--window`, `<hash> <date>` or a JSON object with the file, hashes and date
+fn window_line(
+    target: &Target,
+    commit: &CommitInfo,
+    options: &Options,
+    multi_file: bool,
+) -> String {
+    // Like in `report`, the short hash gets the fixed width as well
+    let (hash, short) = match options.hash_width {
+        Some(width) => {
+            let hash = fixed_width_or_exit(&commit.hash, width);
+            (hash.clone(), hash)
         }
-        println!("{commit_hash}");
+        None => (commit.hash.clone(), commit.short.clone()),
+    };
+    if options.format.name == "json" {
+        return format!(
+            "{{ \"file\": {}, \"commit\": {}, \"short\": {}, \"date\": {} }}",
+            json_string(&target.argument),
+            json_string(&hash),
+            json_string(&short),
+            json_string(&commit.date)
+        );
+    }
+    if multi_file {
+        format!("{}\t{hash} {}", target.argument, commit.date)
     } else {
-        error!("No commits found.");
+        format!("{hash} {}", commit.date)
+    }
+}
+
+/// The number of commits from the latest commit of the first file to the latest commit of the
+/// second, negative if the second is older. If neither commit is in the history of the other,
+/// the commits of the second that are not in the history of the first are counted.
+fn commit_delta(first: &Target, second: &Target, options: &Options) -> String {
+    let commits = [first, second].map(|target| {
+        latest_commit(target, options).unwrap_or_else(|| {
+            error!("No commits found for {}.", target.argument);
+            std::process::exit(1);
+        })
+    });
+    let [from, to] = commits.each_ref().map(|commit| commit.hash.as_str());
+    let count = |from: &str, to: &str| {
+        first
+            .repository
+            .commit_count(from, to)
+            .unwrap_or_else(|e| panic!("Failed to count the commits from {from} to {to}: {e}"))
+    };
+
+    let ahead = count(from, to);
+    let behind = count(to, from);
+    debug!("{} commits from {from} to {to}, {} back", ahead, behind);
+    if behind == 0 {
+        ahead.to_string()
+    } else if ahead == 0 {
+        format!("-{behind}")
+    } else {
+        warning(format!(
+            "The latest commits of {} ({from}) and {} ({to}) are on different lines of history, counting the {ahead} commits of the latter that are not in the history of the former",
+            first.argument, second.argument
+        ));
+        ahead.to_string()
+    }
+}
+
+/// Progress indicator for `--progress`, overwritten in place on stderr.
+/// It is disabled if stderr is not a terminal, e.g. redirected to a log file.
+struct Progress {
+    enabled: bool,
+    done: usize,
+    total: usize,
+}
+
+impl Progress {
+    fn new(enabled: bool, total: usize) -> Self {
+        Progress {
+            enabled: enabled && io::stderr().is_terminal(),
+            done: 0,
+            total,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.done += 1;
+        if self.enabled {
+            let percent = self.done * 100 / self.total;
+            eprint!("\r{}/{} ({percent}%)", self.done, self.total);
+            io::stderr().flush().ok();
+        }
+    }
+
+    /// Clears the indicator, so it doesn't stay in front of the following output
+    fn finish(&self) {
+        if self.enabled {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+fn main() {
+    simple_logger::init().unwrap();
+
+    let args: Vec<String> = env::args().collect();
+
+    let options = parse_args(&args[1..]).unwrap_or_else(|e| {
+        eprintln!("{e}\nUsage: {} {USAGE}", args[0]);
         std::process::exit(1);
+    });
+
+    if let Some((old, new)) = &options.compare_manifests {
+        let diff = ManifestDiff::new(&load_manifest(old), &load_manifest(new));
+        let json = options.format.name == "json";
+        // Nothing is printed for identical files, unless JSON is expected
+        if json || !diff.is_empty() {
+            println!("{}", compare_manifests(&diff, json));
+        }
+        return;
+    }
+
+    if let Some(path) = &options.build_index {
+        build_index(path, &options).unwrap_or_else(|e| {
+            error!("Failed to build the index: {e}");
+            std::process::exit(1);
+        });
+        return;
+    }
+
+    let targets: Vec<Target> = options
+        .filenames
+        .iter()
+        .map(|argument| open_target(argument, &options))
+        .collect();
+
+    let roots = check_single_repository(&targets, options.single_repo || options.commit_delta)
+        .unwrap_or_else(|e| {
+            error!("{e}");
+            std::process::exit(1);
+        });
+    // Ignored files have no history, so they silently don't contribute to the commit
+    if !options.include_ignored && !options.dump_resolved {
+        warn_ignored_files(&targets, &roots);
+    }
+    check_warnings(&options);
+
+    if options.dump_resolved {
+        for target in &targets {
+            println!("{}", dump_resolved(target));
+        }
+        return;
+    }
+
+    // The pathspecs are relative to the base directory, in which git is run
+    if options.print_pathspecs {
+        let multi_file = targets.len() > 1;
+        for target in &targets {
+            let pathspecs: Vec<String> = target
+                .commit_files(&options)
+                .iter()
+                .map(|pathspec| output::shell_quote(pathspec))
+                .collect();
+            if multi_file {
+                println!("{}\t{}", target.argument, pathspecs.join(" "));
+            } else {
+                println!("{}", pathspecs.join(" "));
+            }
+        }
+        return;
+    }
+
+    if options.provenance {
+        for target in &targets {
+            println!("{}", provenance(target, &options));
+        }
+        return;
+    }
+
+    if let Some(count) = options.window {
+        let multi_file = targets.len() > 1;
+        for target in &targets {
+            let commits = target
+                .repository
+                .recent_commits(&target.commit_files(&options), count);
+            for commit in commits {
+                println!("{}", window_line(target, &commit, &options, multi_file));
+            }
+        }
+        return;
+    }
+
+    if options.commit_delta {
+        let delta = commit_delta(&targets[0], &targets[1], &options);
+        check_warnings(&options);
+        println!("{delta}");
+        return;
+    }
+
+    // Only report the uncommitted changes, the commit is not needed
+    if options.dirty_only {
+        let mut dirty_files: Vec<String> = Vec::new();
+        for target in &targets {
+            let Some(files) = target.dirty_check_files(&options) else {
+                continue;
+            };
+            for file in target.repository.dirty_files(files) {
+                if !dirty_files.contains(&file) {
+                    dirty_files.push(file);
+                }
+            }
+        }
+        if !dirty_files.is_empty() {
+            println!("DIRTY");
+            for file in dirty_files {
+                println!("{file}");
+            }
+            if options.fail_on_dirty {
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Collect all records first, so nothing is printed if a warning occurs with --fail-on-warning
+    let mut progress = Progress::new(options.progress, targets.len());
+    let index = options
+        .use_index
+        .as_deref()
+        .and_then(|path| load_index(path, &targets, &options));
+    // The index replaces the walk, only the targets it can't answer are queried one by one
+    let mut batched = match index {
+        Some(_) => None,
+        None => batched_latest_commits(&targets, &options).map(Vec::into_iter),
+    };
+    let mut batched_dirty = batched_dirty_states(&targets, &options).map(Vec::into_iter);
+    let records: Vec<Option<Record>> = targets
+        .iter()
+        .map(|target| {
+            let indexed = index
+                .as_ref()
+                .and_then(|index| indexed_commit(target, &options, index));
+            let commit = match (indexed, &mut batched) {
+                (Some(commit), _) => commit,
+                (None, Some(commits)) => commits.next().flatten(),
+                (None, None) => latest_commit(target, &options),
+            };
+            let dirty = match &mut batched_dirty {
+                Some(states) => states.next().flatten(),
+                None if commit.is_some() => dirty_state(target, &options),
+                None => None,
+            };
+            let record = report(target, &options, commit, dirty).or_else(|| {
+                let placeholder = options.empty_value.as_deref()?;
+                info!(
+                    "No commits found for {}, using the empty value",
+                    target.argument
+                );
+                Some(Record::empty(&target.argument, placeholder))
+            });
+            progress.advance();
+            record
+        })
+        .collect();
+    progress.finish();
+    check_warnings(&options);
+
+    let multi_file = targets.len() > 1;
+    let mut lines = Vec::new();
+    if let Some(header) = records
+        .iter()
+        .filter(|_| options.template.is_none())
+        .flatten()
+        .next()
+        .and_then(|first| options.format.header(first, &options))
+    {
+        lines.push(header);
+    }
+    let mut commits_missing = false;
+    for (target, record) in targets.iter().zip(&records) {
+        match record {
+            Some(record) => lines.push(match &options.template {
+                Some(template) => template.render(record, &options),
+                None => options.format.render(record, &options, multi_file),
+            }),
+            None => {
+                error!("No commits found for {}.", target.argument);
+                commits_missing = true;
+            }
+        }
+    }
+    if commits_missing {
+        // Nothing is written if a result is missing, but the others are still printed
+        if options.output.is_none() {
+            print_lines(&lines);
+        }
+        std::process::exit(1);
+    }
+
+    let records: Vec<Record> = records.into_iter().flatten().collect();
+    if options.dry_run || options.output.is_none() {
+        // Use println to print to stdout instead of stderr (logging)
+        print_lines(&lines);
+    }
+    write_files(&options, &lines, &records, multi_file).unwrap_or_else(|e| {
+        error!("{e}");
+        std::process::exit(1);
+    });
+}
+
+fn print_lines(lines: &[String]) {
+    for line in lines {
+        println!("{line}");
+    }
+}
+
+/// Writes the files of `--output`, `--json-out`, `--env-out` and `--touch` in one transaction, so
+/// either all of them are replaced or none. With `--dry-run`, they are only described.
+fn write_files(
+    options: &Options,
+    lines: &[String],
+    records: &[Record],
+    multi_file: bool,
+) -> Result<(), String> {
+    let render = |format: &str| -> String {
+        let format = Format::parse(format).expect("Built-in format");
+        records
+            .iter()
+            .map(|record| format!("{}\n", format.render(record, options, multi_file)))
+            .collect()
+    };
+    let mut contents = Vec::new();
+    if let Some(path) = &options.output {
+        let text: String = lines.iter().map(|line| format!("{line}\n")).collect();
+        contents.push((path, text));
+    }
+    if let Some(path) = &options.json_out {
+        contents.push((path, render("json")));
+    }
+    if let Some(path) = &options.env_out {
+        contents.push((path, render("env")));
+    }
+
+    if options.dry_run {
+        for (path, text) in &contents {
+            info!(
+                "Dry run: would write {} lines ({} bytes) to {path}",
+                text.lines().count(),
+                text.len()
+            );
+        }
+        if let Some(path) = &options.touch {
+            info!("Dry run: would touch {path}");
+        }
+        if contents.is_empty() && options.touch.is_none() {
+            info!("Dry run: nothing would be written besides the output on stdout");
+        }
+        return Ok(());
+    }
+
+    let mut transaction = sink::Transaction::default();
+    for (path, text) in &contents {
+        transaction.write(Path::new(path), text.as_bytes())?;
+    }
+    if let Some(path) = &options.touch {
+        transaction.touch(Path::new(path))?;
+    }
+    transaction.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pathspec_magic_is_detected() {
+        assert!(is_pathspec_magic("src/*.rs"));
+        assert!(is_pathspec_magic("file?.txt"));
+        assert!(is_pathspec_magic(":(exclude)target"));
+        assert!(is_pathspec_magic(":!target"));
+        assert!(!is_pathspec_magic("src/main.rs"));
+        assert!(!is_pathspec_magic("../a:b.txt"));
+    }
+
+    #[test]
+    fn format_epoch_date_is_utc() {
+        assert_eq!(format_epoch_date(0), "1970-01-01");
+        assert_eq!(format_epoch_date(86399), "1970-01-01");
+        assert_eq!(format_epoch_date(951782400), "2000-02-29");
+        assert_eq!(format_epoch_date(-1), "1969-12-31");
+    }
+
+    #[test]
+    fn fixed_width_cuts_the_hash() {
+        let hash = "5d6256345067a82563106c868f2ad1b384286dce";
+        assert_eq!(fixed_width(hash, 12).unwrap(), "5d6256345067");
+        assert_eq!(fixed_width(hash, 40).unwrap(), hash);
+        assert!(fixed_width(hash, 41).is_err());
     }
 }
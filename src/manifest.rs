@@ -0,0 +1,434 @@
+use std::{fs, path::Path};
+
+/// Top-level key of the path-keyed format, an array of tables with a `path` each
+const FILES_KEY: &str = "files";
+
+/// Formats of dependencies files, see `ManifestFormat::PRECEDENCE`
+#[derive(Clone, Copy, PartialEq)]
+pub enum ManifestFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ManifestFormat {
+    /// The order in which the formats are used if a directory contains several dependencies
+    /// files. Only TOML is parsed so far, the others are detected so they are never used or
+    /// ignored silently.
+    pub const PRECEDENCE: [ManifestFormat; 3] = [
+        ManifestFormat::Toml,
+        ManifestFormat::Json,
+        ManifestFormat::Yaml,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Self::PRECEDENCE
+            .into_iter()
+            .find(|format| format.name() == name)
+            .ok_or_else(|| {
+                format!("Invalid dependencies format: {name} (expected toml, json or yaml)")
+            })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ManifestFormat::Toml => "toml",
+            ManifestFormat::Json => "json",
+            ManifestFormat::Yaml => "yaml",
+        }
+    }
+
+    /// The names of the dependencies files of the format, besides `.deps.toml`
+    pub fn file_names(self) -> &'static [&'static str] {
+        match self {
+            ManifestFormat::Toml => &[".deps.toml"],
+            ManifestFormat::Json => &[".deps.json"],
+            ManifestFormat::Yaml => &[".deps.yaml", ".deps.yml"],
+        }
+    }
+}
+
+/// One entry of the dependencies file
+pub struct Entry {
+    /// The file the entry belongs to
+    pub key: String,
+    /// Where the entry is defined, for diagnostics
+    pub location: String,
+    pub dependencies: Option<Vec<String>>,
+    /// Former paths of the file, only used to query the history
+    pub aliases: Vec<String>,
+    /// Whether uncommitted changes are reported, `dirty = false` disables the dirty check
+    pub dirty: bool,
+}
+
+/// The parsed dependencies file.
+/// Entries are either keyed by filename (`["file.typ"]`) or listed in the path-keyed format (`[[files]]` with `path = "file.typ"`).
+pub struct Manifest {
+    pub entries: Vec<Entry>,
+}
+
+impl Manifest {
+    /// Reads and parses the dependencies file, None if it doesn't exist
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        Self::parse(&content)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse {}: {e}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let table = content.parse::<toml::Table>().map_err(|e| e.to_string())?;
+
+        let mut entries = Vec::new();
+        for (key, value) in &table {
+            match value {
+                // A table called "files" is still an entry for a file called "files"
+                toml::Value::Array(files) if key == FILES_KEY => {
+                    for (index, file) in files.iter().enumerate() {
+                        let location = format!("{FILES_KEY}[{index}]");
+                        let path = file
+                            .get("path")
+                            .and_then(|path| path.as_str())
+                            .ok_or_else(|| format!("{location} has no path"))?;
+                        let path = normalize(path);
+                        // Files are looked up by name in the dependencies file of their own
+                        // directory, so an entry for a file elsewhere would never be used
+                        if path.contains('/') {
+                            return Err(format!(
+                                "{location}: path {path:?} is not a file next to the dependencies file, list it in the dependencies file of its directory"
+                            ));
+                        }
+                        entries.push(Entry::parse(path, location, file)?);
+                    }
+                }
+                _ => entries.push(Entry::parse(key.clone(), format!("[\"{key}\"]"), value)?),
+            }
+        }
+
+        let manifest = Manifest { entries };
+        manifest.check_collisions()?;
+        Ok(manifest)
+    }
+
+    /// TOML itself rejects duplicate keys, but not two entries for the same path
+    fn check_collisions(&self) -> Result<(), String> {
+        let mut collisions = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let first = self.entries.iter().position(|other| other.key == entry.key);
+            if first != Some(index) {
+                continue;
+            }
+            let locations: Vec<&str> = self
+                .entries
+                .iter()
+                .filter(|other| other.key == entry.key)
+                .map(|other| other.location.as_str())
+                .collect();
+            if locations.len() > 1 {
+                collisions.push(format!("{:?} ({})", entry.key, locations.join(", ")));
+            }
+        }
+
+        if collisions.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Multiple entries for the same file: {}",
+                collisions.join("; ")
+            ))
+        }
+    }
+
+    /// Finds the entry for a file
+    pub fn get(&self, filename: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.key == filename)
+    }
+}
+
+impl Entry {
+    fn parse(key: String, location: String, value: &toml::Value) -> Result<Self, String> {
+        Ok(Entry {
+            dependencies: string_array(value, "dependencies", &location)?,
+            aliases: string_array(value, "aliases", &location)?.unwrap_or_default(),
+            dirty: boolean(value, "dirty", &location)?.unwrap_or(true),
+            key,
+            location,
+        })
+    }
+}
+
+/// The semantic difference between two dependencies files, by entry
+pub struct ManifestDiff {
+    /// Keys of the entries only in the new file
+    pub added: Vec<String>,
+    /// Keys of the entries only in the old file
+    pub removed: Vec<String>,
+    /// Entries in both files with different dependencies or aliases
+    pub changed: Vec<EntryDiff>,
+}
+
+/// The changes of one entry. The order of the lists doesn't matter, as it doesn't change the result.
+pub struct EntryDiff {
+    pub key: String,
+    pub added_dependencies: Vec<String>,
+    pub removed_dependencies: Vec<String>,
+    pub added_aliases: Vec<String>,
+    pub removed_aliases: Vec<String>,
+    /// The new `dirty` setting, if it changed
+    pub dirty: Option<bool>,
+    /// Whether the entry now monitors the whole directory, as it has no `dependencies`, if that
+    /// changed (see `resolve_files`)
+    pub fallback: Option<bool>,
+}
+
+impl ManifestDiff {
+    /// Compares the entries of both files, in the order of the old file followed by new entries.
+    /// An entry without dependencies differs from one with an empty list, as it monitors the whole
+    /// directory instead of only the file.
+    pub fn new(old: &Manifest, new: &Manifest) -> Self {
+        let only_in = |values: &[String], other: &[String]| -> Vec<String> {
+            values
+                .iter()
+                .filter(|value| !other.contains(value))
+                .cloned()
+                .collect()
+        };
+
+        let mut diff = ManifestDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+        for entry in &old.entries {
+            let Some(other) = new.get(&entry.key) else {
+                diff.removed.push(entry.key.clone());
+                continue;
+            };
+            let dependencies = entry.dependencies.as_deref().unwrap_or_default();
+            let other_dependencies = other.dependencies.as_deref().unwrap_or_default();
+            let change = EntryDiff {
+                key: entry.key.clone(),
+                added_dependencies: only_in(other_dependencies, dependencies),
+                removed_dependencies: only_in(dependencies, other_dependencies),
+                added_aliases: only_in(&other.aliases, &entry.aliases),
+                removed_aliases: only_in(&entry.aliases, &other.aliases),
+                dirty: (entry.dirty != other.dirty).then_some(other.dirty),
+                fallback: (entry.dependencies.is_none() != other.dependencies.is_none())
+                    .then_some(other.dependencies.is_none()),
+            };
+            if !change.is_empty() {
+                diff.changed.push(change);
+            }
+        }
+        for entry in &new.entries {
+            if old.get(&entry.key).is_none() {
+                diff.added.push(entry.key.clone());
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl EntryDiff {
+    fn is_empty(&self) -> bool {
+        self.added_dependencies.is_empty()
+            && self.removed_dependencies.is_empty()
+            && self.added_aliases.is_empty()
+            && self.removed_aliases.is_empty()
+            && self.dirty.is_none()
+            && self.fallback.is_none()
+    }
+}
+
+/// Writes the short form of pathspec magic (`:!path`, `:^path`, `:/path`) in the long form
+/// (`:(exclude)path`, `:(top)path`), which means the same to git but is easier to read and
+/// doesn't need quoting in a shell. Other pathspecs are returned as they are.
+pub fn long_form(pathspec: &str) -> String {
+    let Some(rest) = pathspec.strip_prefix(':') else {
+        return pathspec.to_string();
+    };
+    let signature_length = rest
+        .find(|c| !matches!(c, '!' | '^' | '/'))
+        .unwrap_or(rest.len());
+    let (signature, path) = rest.split_at(signature_length);
+    if signature.is_empty() {
+        return pathspec.to_string();
+    }
+    // The signature may be terminated by another `:`
+    let path = path.strip_prefix(':').unwrap_or(path);
+
+    let mut magic = Vec::new();
+    if signature.contains('/') {
+        magic.push("top");
+    }
+    if signature.contains(['!', '^']) {
+        magic.push("exclude");
+    }
+    format!(":({}){path}", magic.join(","))
+}
+
+/// Strips leading `./` so that `./file.typ` and `file.typ` are the same path
+fn normalize(path: &str) -> String {
+    let mut path = path;
+    while let Some(stripped) = path.strip_prefix("./") {
+        path = stripped;
+    }
+    path.to_string()
+}
+
+/// Gets the key of a dependencies file entry as a boolean
+fn boolean(entry: &toml::Value, key: &str, location: &str) -> Result<Option<bool>, String> {
+    entry
+        .get(key)
+        .map(|value| {
+            value
+                .as_bool()
+                .ok_or_else(|| format!("{key} of {location} must be true or false"))
+        })
+        .transpose()
+}
+
+/// Gets the key of a dependencies file entry as an array of strings
+fn string_array(
+    entry: &toml::Value,
+    key: &str,
+    location: &str,
+) -> Result<Option<Vec<String>>, String> {
+    let Some(values) = entry.get(key) else {
+        return Ok(None);
+    };
+    let values = values
+        .as_array()
+        .ok_or_else(|| format!("{key} of {location} must be an array of strings"))?;
+    values
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("{key} of {location} must be strings"))
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+/// The files monitored for one entry of the dependencies file
+pub struct ResolvedFiles {
+    pub files: Vec<String>,
+    /// Former paths of the files, only used to query the history
+    pub aliases: Vec<String>,
+    /// Whether no entry was found and the base directory is monitored instead
+    pub from_fallback: bool,
+    /// Whether uncommitted changes are reported, see `Entry::dirty`
+    pub dirty_check: bool,
+}
+
+impl ResolvedFiles {
+    /// The files and their aliases, i.e., all pathspecs of the history query
+    pub fn history_files(&self) -> Vec<String> {
+        self.files.iter().chain(&self.aliases).cloned().collect()
+    }
+}
+
+/// Determines whether any dependencies for the file are specified and collects all files that shall be monitored.
+/// The dependencies are nested in one extra struct so we can extend this later on without breaking the existing toml files.
+pub fn resolve_files(
+    manifest: Option<&Manifest>,
+    filename: &str,
+    base_directory_string: &str,
+) -> ResolvedFiles {
+    let entry = manifest.and_then(|manifest| manifest.get(filename));
+
+    match entry.and_then(|entry| entry.dependencies.as_ref().map(|deps| (entry, deps))) {
+        Some((entry, deps)) => {
+            let mut files = vec![filename.to_string()]; // Always include the filename itself
+            files.extend(deps.iter().map(|dependency| long_form(dependency)));
+            ResolvedFiles {
+                files,
+                aliases: entry.aliases.clone(),
+                from_fallback: false,
+                dirty_check: entry.dirty,
+            }
+        }
+        None => ResolvedFiles {
+            files: vec![base_directory_string.to_string()],
+            aliases: Vec::new(),
+            from_fallback: true,
+            dirty_check: entry.is_none_or(|entry| entry.dirty),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_paths_are_rejected() {
+        let error = Manifest::parse(
+            r#"
+            ["file1.typ"]
+            dependencies = ["a.typ"]
+
+            [[files]]
+            path = "./file1.typ"
+            dependencies = ["b.typ"]
+            "#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error,
+            r#"Multiple entries for the same file: "file1.typ" (["file1.typ"], files[0])"#
+        );
+
+        let error = Manifest::parse(
+            r#"
+            [[files]]
+            path = "file2.typ"
+            [[files]]
+            path = "file2.typ"
+            "#,
+        )
+        .err()
+        .unwrap();
+        assert!(error.contains("files[0], files[1]"), "{error}");
+    }
+
+    #[test]
+    fn path_keyed_entries_must_be_next_to_the_manifest() {
+        let error = Manifest::parse("[[files]]\npath = \"docs/x.typ\"\n")
+            .err()
+            .unwrap();
+        assert!(
+            error.starts_with("files[0]: path \"docs/x.typ\""),
+            "{error}"
+        );
+
+        let manifest = Manifest::parse("[[files]]\npath = \"./x.typ\"\n").unwrap();
+        assert!(manifest.get("x.typ").is_some());
+    }
+
+    #[test]
+    fn removing_the_dependencies_is_a_change() {
+        let old = Manifest::parse("[\"x.typ\"]\ndependencies = []\n").unwrap();
+        let new = Manifest::parse("[\"x.typ\"]\n").unwrap();
+
+        let diff = ManifestDiff::new(&old, &new);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].fallback, Some(true));
+        assert_eq!(
+            ManifestDiff::new(&new, &old).changed[0].fallback,
+            Some(false)
+        );
+        assert!(ManifestDiff::new(&new, &new).changed.is_empty());
+    }
+}
@@ -0,0 +1,924 @@
+use crate::cli::{Options, Value};
+use change_monitor::vcs::{CommitInfo, Diffstat};
+
+/// An output format, i.e., how the records of the files given on the command line are printed
+#[derive(Clone, Copy)]
+pub struct Format {
+    pub name: &'static str,
+    /// Whether the format reports the dirty state for the selected value
+    needs_dirty_check: fn(Value) -> bool,
+    /// Printed once before the records (built from the first one), e.g. column names
+    header: Option<fn(&Record, &Options) -> String>,
+    /// Renders one record. With several files, the record has to identify its file.
+    render: fn(&Record, &Options, bool) -> String,
+}
+
+/// All formats that can be selected with `--format`. A new format only needs an entry here.
+pub const FORMATS: &[Format] = &[
+    // The value, the markers and the link separated by spaces
+    Format::PLAIN,
+    // One JSON object per line
+    Format {
+        name: "json",
+        needs_dirty_check: always,
+        header: None,
+        render: json,
+    },
+    // Tab separated columns below a header line
+    Format {
+        name: "tsv",
+        needs_dirty_check: always,
+        header: Some(tsv_header),
+        render: tsv,
+    },
+    // Variable assignments to be `eval`ed by a POSIX shell
+    Format {
+        name: "shell",
+        needs_dirty_check: always,
+        header: None,
+        render: shell,
+    },
+    // Variable definitions to be `include`d by a Makefile
+    Format {
+        name: "make",
+        needs_dirty_check: always,
+        header: None,
+        render: make,
+    },
+    // `KEY VALUE` lines for the workspace status command of Bazel (and Buck)
+    Format {
+        name: "stamp",
+        needs_dirty_check: always,
+        header: None,
+        render: stamp,
+    },
+    // `key=value` lines to be loaded by `java.util.Properties`
+    Format {
+        name: "properties",
+        needs_dirty_check: always,
+        header: None,
+        render: properties,
+    },
+    // `KEY=value` lines for `docker --env-file` or systemd's `EnvironmentFile`
+    Format {
+        name: "env",
+        needs_dirty_check: always,
+        header: None,
+        render: env,
+    },
+    // `'file'='value'` lines, keyed by the file instead of fixed variable names
+    Format {
+        name: "pairs",
+        needs_dirty_check: Value::is_hash,
+        header: None,
+        render: pairs,
+    },
+];
+
+fn always(_: Value) -> bool {
+    true
+}
+
+impl Format {
+    pub const PLAIN: Format = Format {
+        name: "plain",
+        needs_dirty_check: Value::is_hash,
+        header: None,
+        render: plain,
+    };
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        FORMATS
+            .iter()
+            .find(|format| format.name == name)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = FORMATS.iter().map(|format| format.name).collect();
+                format!("Invalid format: {name} (expected {})", names.join(", "))
+            })
+    }
+
+    pub fn needs_dirty_check(self, value: Value) -> bool {
+        (self.needs_dirty_check)(value)
+    }
+
+    pub fn header(self, first: &Record, options: &Options) -> Option<String> {
+        self.header.map(|header| header(first, options))
+    }
+
+    pub fn render(self, record: &Record, options: &Options, multi_file: bool) -> String {
+        (self.render)(record, options, multi_file)
+    }
+}
+
+/// Everything reported for one file given on the command line
+pub struct Record {
+    /// The file as given on the command line
+    pub file: String,
+    pub commit: CommitInfo,
+    /// The commit hash, abbreviated to `--hash-width`
+    pub hash: String,
+    /// Date of the latest commit, or from `SOURCE_DATE_EPOCH`
+    pub date: String,
+    /// The selected value, e.g. the commit hash or the date
+    pub value: String,
+    /// Whether there are uncommitted changes, None if not checked
+    pub dirty: Option<bool>,
+    /// Link to the commit, if a template was given
+    pub url: Option<String>,
+    /// The changes of the latest commit to the monitored files, with `--with-diffstat`
+    pub diffstat: Option<Diffstat>,
+    /// The checked out branch, with `--with-branch`
+    pub branch: Option<String>,
+    /// The latest commits of the file and of its dependencies, with `--breakdown`
+    pub breakdown: Option<Breakdown>,
+}
+
+impl Record {
+    /// The record of a file without commits for `--empty-value`: the placeholder is the value,
+    /// commit and short hash, the date is empty and nothing else is reported
+    pub fn empty(file: &str, placeholder: &str) -> Self {
+        Record {
+            file: file.to_string(),
+            commit: CommitInfo {
+                hash: placeholder.to_string(),
+                short: placeholder.to_string(),
+                date: String::new(),
+                timestamp: 0,
+                name: String::new(),
+                email: String::new(),
+            },
+            hash: placeholder.to_string(),
+            date: String::new(),
+            value: placeholder.to_string(),
+            dirty: None,
+            url: None,
+            diffstat: None,
+            branch: None,
+            breakdown: None,
+        }
+    }
+}
+
+/// The reported commit split into the file itself (with its aliases) and its dependencies.
+/// The hashes are abbreviated like the commit, None if there is no such commit.
+pub struct Breakdown {
+    pub file: Option<String>,
+    pub dependencies: Option<String>,
+}
+
+/// If a hash is printed and the working tree (or the index only) is dirty, the "DIRTY" marker.
+/// If clean, the clean marker, if any.
+fn dirty_marker<'a>(record: &Record, options: &'a Options) -> Option<&'a str> {
+    if !options.value.is_hash() {
+        return None;
+    }
+    match record.dirty? {
+        true => Some("DIRTY"),
+        false => options.clean_marker.as_deref(),
+    }
+}
+
+fn plain(record: &Record, options: &Options, multi_file: bool) -> String {
+    let mut output = String::new();
+    // With several files, each line is prefixed by the file it belongs to
+    if multi_file {
+        output.push_str(&record.file);
+        output.push('\t');
+    }
+    output.push_str(&record.value);
+
+    // The latest commits of the file and its dependencies as two more columns, `-` if there is none
+    if let Some(breakdown) = &record.breakdown {
+        for hash in [&breakdown.file, &breakdown.dependencies] {
+            output.push(' ');
+            output.push_str(hash.as_deref().unwrap_or("-"));
+        }
+    }
+
+    if let Some(marker) = dirty_marker(record, options) {
+        output.push(' ');
+        output.push_str(marker);
+    }
+
+    // E.g. `3 files +10 -2`
+    if let Some(diffstat) = &record.diffstat {
+        output.push_str(&format!(
+            " {} file{} +{} -{}",
+            diffstat.files_changed,
+            if diffstat.files_changed == 1 { "" } else { "s" },
+            diffstat.insertions,
+            diffstat.deletions
+        ));
+    }
+
+    if let Some(branch) = &record.branch {
+        output.push(' ');
+        output.push_str(branch);
+    }
+
+    // Append the link to the commit as an extra column
+    if let Some(url) = &record.url {
+        output.push(' ');
+        output.push_str(url);
+    }
+    output
+}
+
+/// The named values of a record, shared by the formats with keys.
+/// `DIRTY` is `1` or `0`, the formats may translate it.
+fn fields(record: &Record, options: &Options) -> Vec<(&'static str, String)> {
+    columns(record, options)
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect()
+}
+
+/// The names of all values the options select, the same for every record, with the values of
+/// the record. A value is None if the record has none, e.g. the dirty state of an entry with
+/// `dirty = false`, or everything but the placeholder in a record of `--empty-value`.
+fn columns(record: &Record, options: &Options) -> Vec<(&'static str, Option<String>)> {
+    let mut columns = vec![
+        ("COMMIT", Some(record.hash.clone())),
+        ("SHORT", Some(record.commit.short.clone())),
+        ("DATE", Some(record.date.clone())),
+    ];
+    if !options.no_dirty_check {
+        columns.push((
+            "DIRTY",
+            record.dirty.map(|dirty| u8::from(dirty).to_string()),
+        ));
+    }
+    match options.value {
+        Value::TreeHash => columns.push(("TREE_HASH", Some(record.value.clone()))),
+        Value::Fingerprint => columns.push(("FINGERPRINT", Some(record.value.clone()))),
+        // With a unit, the age is text
+        Value::AgeSeconds if options.age_format.is_some() => {
+            columns.push(("AGE", Some(record.value.clone())));
+        }
+        Value::AgeSeconds => columns.push(("AGE_SECONDS", Some(record.value.clone()))),
+        Value::MergeBase => columns.push(("MERGE_BASE", Some(record.value.clone()))),
+        Value::Parent => columns.push(("PARENT", Some(record.value.clone()))),
+        Value::Hash | Value::Date => {}
+    }
+    if options.identity.is_some() {
+        columns.push(("NAME", Some(record.commit.name.clone())));
+        columns.push(("EMAIL", Some(record.commit.email.clone())));
+    }
+    if options.with_diffstat {
+        let diffstat = record.diffstat.as_ref();
+        columns.push((
+            "FILES_CHANGED",
+            diffstat.map(|diffstat| diffstat.files_changed.to_string()),
+        ));
+        columns.push((
+            "INSERTIONS",
+            diffstat.map(|diffstat| diffstat.insertions.to_string()),
+        ));
+        columns.push((
+            "DELETIONS",
+            diffstat.map(|diffstat| diffstat.deletions.to_string()),
+        ));
+    }
+    if options.with_branch.is_some() {
+        columns.push(("BRANCH", record.branch.clone()));
+    }
+    if options.breakdown {
+        let breakdown = record.breakdown.as_ref();
+        let hash = |hash: &Option<String>| hash.clone().unwrap_or_default();
+        columns.push((
+            "FILE_COMMIT",
+            breakdown.map(|breakdown| hash(&breakdown.file)),
+        ));
+        columns.push((
+            "DEPS_COMMIT",
+            breakdown.map(|breakdown| hash(&breakdown.dependencies)),
+        ));
+    }
+    if options.commit_url_template.is_some() {
+        columns.push(("URL", record.url.clone()));
+    }
+    columns
+}
+
+/// A user-supplied template with `{name}` placeholders, see `--template`
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+/// Placeholders a template may use: the file, the selected value and all fields
+const PLACEHOLDERS: &[&str] = &[
+    "file",
+    "value",
+    "commit",
+    "short",
+    "date",
+    "dirty",
+    "tree_hash",
+    "fingerprint",
+    "age_seconds",
+    "age",
+    "merge_base",
+    "parent",
+    "name",
+    "email",
+    "files_changed",
+    "insertions",
+    "deletions",
+    "branch",
+    "file_commit",
+    "deps_commit",
+    "url",
+];
+
+impl Template {
+    /// Parses a template, `{{` and `}}` are literal braces. Unknown placeholders are an error.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(format!("Unclosed placeholder in template: {{{name}"))
+                            }
+                        }
+                    }
+                    if !PLACEHOLDERS.contains(&name.as_str()) {
+                        return Err(format!(
+                            "Unknown template placeholder: {{{name}}} (expected any of {})",
+                            PLACEHOLDERS.join(", ")
+                        ));
+                    }
+                    segments.push(Segment::Text(std::mem::take(&mut literal)));
+                    segments.push(Segment::Placeholder(name));
+                }
+                '}' => {
+                    return Err("Unmatched } in template, write }} for a literal brace".to_string())
+                }
+                c => literal.push(c),
+            }
+        }
+        segments.push(Segment::Text(literal));
+        Ok(Template { segments })
+    }
+
+    /// Whether the template contains the placeholder
+    pub fn uses(&self, placeholder: &str) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Placeholder(name) if name == placeholder))
+    }
+
+    /// Fills in the placeholders. `{dirty}` is `DIRTY` or the clean marker, like in the plain
+    /// format, placeholders of values that are not reported (e.g. `{url}` without a template or
+    /// `{dirty}` without a dirty check) are empty.
+    pub fn render(&self, record: &Record, options: &Options) -> String {
+        let fields = fields(record, options);
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Placeholder(name) => match name.as_str() {
+                    "file" => output.push_str(&record.file),
+                    "value" => output.push_str(&record.value),
+                    "dirty" => match record.dirty {
+                        Some(true) => output.push_str("DIRTY"),
+                        Some(false) => {
+                            output.push_str(options.clean_marker.as_deref().unwrap_or_default())
+                        }
+                        // Not checked, e.g. excluded from the dirty check
+                        None => {}
+                    },
+                    name => {
+                        if let Some((_, value)) = fields
+                            .iter()
+                            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+                        {
+                            output.push_str(value);
+                        }
+                    }
+                },
+            }
+        }
+        output
+    }
+}
+
+/// The file and the named values, always with the file, so all lines have the same fields
+fn json(record: &Record, options: &Options, _multi_file: bool) -> String {
+    let mut members = vec![format!("\"file\": {}", json_string(&record.file))];
+    for (name, value) in fields(record, options) {
+        let value = match name {
+            "DIRTY" => (value == "1").to_string(),
+            // A number, unless it is the `--empty-value`
+            "AGE_SECONDS" if value.parse::<u64>().is_ok() => value,
+            "FILES_CHANGED" | "INSERTIONS" | "DELETIONS" => value,
+            "FILE_COMMIT" | "DEPS_COMMIT" if value.is_empty() => "null".to_string(),
+            _ => json_string(&value),
+        };
+        members.push(format!("\"{}\": {value}", name.to_ascii_lowercase()));
+    }
+    format!("{{ {} }}", members.join(", "))
+}
+
+/// The columns are the same for all records, so the first one is as good as any
+fn tsv_header(first: &Record, options: &Options) -> String {
+    let mut names = vec!["file".to_string()];
+    names.extend(
+        columns(first, options)
+            .into_iter()
+            .map(|(name, _)| name.to_ascii_lowercase()),
+    );
+    names.join("\t")
+}
+
+/// The file and the values in the order of the header, empty if the record has no such value.
+/// Tabs, line breaks and backslashes are escaped as `\t`, `\n`, `\r` and `\\`, as in the IANA
+/// TSV convention.
+fn tsv(record: &Record, options: &Options, _multi_file: bool) -> String {
+    let escape = |value: &str| {
+        value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    };
+    let mut cells = vec![escape(&record.file)];
+    cells.extend(
+        columns(record, options)
+            .into_iter()
+            .map(|(_, value)| escape(&value.unwrap_or_default())),
+    );
+    cells.join("\t")
+}
+
+fn shell(record: &Record, options: &Options, multi_file: bool) -> String {
+    let mut variables: Vec<(&str, String)> = Vec::new();
+    if multi_file {
+        variables.push(("FILE", shell_quote(&record.file)));
+    }
+    for (name, value) in fields(record, options) {
+        // Numbers are not quoted, for arithmetic. The age may be the `--empty-value`.
+        let value = match name {
+            "DIRTY" => value,
+            "AGE_SECONDS" if value.parse::<u64>().is_ok() => value,
+            _ => shell_quote(&value),
+        };
+        variables.push((name, value));
+    }
+
+    let assignments: Vec<String> = variables
+        .into_iter()
+        .map(|(name, value)| format!("{}{name}={value}", options.shell_prefix))
+        .collect();
+    assignments.join("; ")
+}
+
+/// The file and the selected value, both single-quoted like in the shell format, so a file
+/// containing `=` or a value with spaces can't be misread. Always with the file, even for one file.
+/// A hash is followed by the markers like in the plain format, inside the quotes.
+fn pairs(record: &Record, options: &Options, _multi_file: bool) -> String {
+    let mut value = record.value.clone();
+    if let Some(marker) = dirty_marker(record, options) {
+        value.push(' ');
+        value.push_str(marker);
+    }
+    format!("{}={}", shell_quote(&record.file), shell_quote(&value))
+}
+
+/// One `KEY VALUE` line per value. Keys with the `STABLE_` prefix end up in Bazel's
+/// stable-status.txt, and a change of them rebuilds the stamped targets. The age changes
+/// all the time (also as `AGE` with `--age-format`), so it is volatile and does not trigger
+/// rebuilds on its own.
+/// With several files, the key contains the file, e.g. `STABLE_CHANGE_MONITOR_DOCS_MAIN_TYP_COMMIT`.
+fn stamp(record: &Record, options: &Options, multi_file: bool) -> String {
+    let file = key_file(record, multi_file);
+    let lines: Vec<String> = fields(record, options)
+        .into_iter()
+        .map(|(name, value)| {
+            let volatile = name == "AGE_SECONDS" || name == "AGE";
+            let prefix = if volatile { "" } else { "STABLE_" };
+            // The value is the rest of the line, so it must not contain a line break
+            let value = value.replace(['\n', '\r'], " ");
+            format!("{prefix}CHANGE_MONITOR_{file}{name} {value}")
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// `CHANGE_MONITOR_COMMIT := ...` lines. Immediate assignments, so nothing is expanded later,
+/// and `$` is escaped as `$$` and `#` as `\#` because of the evaluation at the definition.
+fn make(record: &Record, options: &Options, multi_file: bool) -> String {
+    let file = key_file(record, multi_file);
+    let lines: Vec<String> = fields(record, options)
+        .into_iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace(['\n', '\r'], " ")
+                .replace('$', "$$")
+                .replace('#', "\\#");
+            format!("CHANGE_MONITOR_{file}{name} := {value}")
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// `CHANGE_MONITOR_COMMIT=...` lines. Env files are not parsed by a shell, so the values are
+/// taken literally and there is no quoting, line breaks are replaced by spaces.
+fn env(record: &Record, options: &Options, multi_file: bool) -> String {
+    let file = key_file(record, multi_file);
+    let lines: Vec<String> = fields(record, options)
+        .into_iter()
+        .map(|(name, value)| {
+            let value = value.replace(['\n', '\r'], " ");
+            format!("CHANGE_MONITOR_{file}{name}={value}")
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// With several files, the key segment of the file followed by `_`, empty otherwise
+fn key_file(record: &Record, multi_file: bool) -> String {
+    if multi_file {
+        format!("{}_", stamp_key(&record.file))
+    } else {
+        String::new()
+    }
+}
+
+/// Turns a file name into a key segment: uppercase letters, digits and `_`
+fn stamp_key(file: &str) -> String {
+    file.trim_start_matches("./")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// One `key=value` line per value, e.g. `change.monitor.commit=...`. The keys are the lowercase
+/// names of the shell format with `.` instead of `_`, below the prefix and, with several files, the file.
+fn properties(record: &Record, options: &Options, multi_file: bool) -> String {
+    let mut prefix = options.properties_prefix.clone();
+    if multi_file {
+        prefix = join_key(&prefix, &record.file);
+    }
+    let lines: Vec<String> = fields(record, options)
+        .into_iter()
+        .map(|(name, value)| {
+            let key = join_key(&prefix, &name.to_ascii_lowercase().replace('_', "."));
+            let value = match (name, value.as_str()) {
+                ("DIRTY", "1") => "true".to_string(),
+                ("DIRTY", _) => "false".to_string(),
+                _ => value,
+            };
+            format!(
+                "{}={}",
+                properties_escape(&key, true),
+                properties_escape(&value, false)
+            )
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Joins two parts of a properties key with `.`, an empty prefix is left out
+fn join_key(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+/// Escapes a key or value like `java.util.Properties::store`: `\`, the separators `=` and `:`,
+/// the comment characters `#` and `!` and whitespace are escaped with a backslash (spaces only in
+/// keys and at the start of values), everything outside printable ASCII as UTF-16 `\uXXXX`.
+fn properties_escape(text: &str, is_key: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (i, c) in text.chars().enumerate() {
+        match c {
+            ' ' if is_key || i == 0 => escaped.push_str("\\ "),
+            '\\' | '=' | ':' | '#' | '!' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\x0c' => escaped.push_str("\\f"),
+            ' '..='~' => escaped.push(c),
+            _ => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    escaped.push_str(&format!("\\u{unit:04X}"));
+                }
+            }
+        }
+    }
+    escaped
+}
+
+/// Whether the string is a valid shell variable name (or prefix of one)
+pub fn is_shell_name(name: &str) -> bool {
+    !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Quotes a string for a POSIX shell. Inside single quotes, nothing is special except
+/// the single quote itself, which is written as `'\''` (end quote, escaped quote, start quote).
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Quotes and escapes a string for use in JSON output
+pub fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::parse_args;
+
+    fn options(args: &[&str]) -> Options {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args).unwrap()
+    }
+
+    const HASH: &str = "5d6256345067a82563106c868f2ad1b384286dce";
+
+    /// A record of a dirty file, built by hand instead of from a repository
+    fn record() -> Record {
+        Record {
+            file: "src/main.rs".to_string(),
+            commit: CommitInfo {
+                hash: HASH.to_string(),
+                short: "5d62563".to_string(),
+                date: "2024-01-02".to_string(),
+                timestamp: 1704153600,
+                name: "Test".to_string(),
+                email: "test@example.com".to_string(),
+            },
+            hash: HASH.to_string(),
+            date: "2024-01-02".to_string(),
+            value: HASH.to_string(),
+            dirty: Some(true),
+            url: None,
+            diffstat: None,
+            branch: None,
+            breakdown: None,
+        }
+    }
+
+    #[test]
+    fn every_format_renders_the_record() {
+        let options = options(&["file"]);
+        let record = record();
+        for format in FORMATS {
+            let expected = match format.name {
+                "plain" => format!("{HASH} DIRTY"),
+                "json" => format!(
+                    r#"{{ "file": "src/main.rs", "commit": "{HASH}", "short": "5d62563", "date": "2024-01-02", "dirty": true }}"#
+                ),
+                "tsv" => format!("src/main.rs\t{HASH}\t5d62563\t2024-01-02\t1"),
+                "shell" => format!("COMMIT='{HASH}'; SHORT='5d62563'; DATE='2024-01-02'; DIRTY=1"),
+                "make" => format!(
+                    "CHANGE_MONITOR_COMMIT := {HASH}\nCHANGE_MONITOR_SHORT := 5d62563\n\
+                     CHANGE_MONITOR_DATE := 2024-01-02\nCHANGE_MONITOR_DIRTY := 1"
+                ),
+                "stamp" => format!(
+                    "STABLE_CHANGE_MONITOR_COMMIT {HASH}\nSTABLE_CHANGE_MONITOR_SHORT 5d62563\n\
+                     STABLE_CHANGE_MONITOR_DATE 2024-01-02\nSTABLE_CHANGE_MONITOR_DIRTY 1"
+                ),
+                "properties" => format!(
+                    "change.monitor.commit={HASH}\nchange.monitor.short=5d62563\n\
+                     change.monitor.date=2024-01-02\nchange.monitor.dirty=true"
+                ),
+                "env" => format!(
+                    "CHANGE_MONITOR_COMMIT={HASH}\nCHANGE_MONITOR_SHORT=5d62563\n\
+                     CHANGE_MONITOR_DATE=2024-01-02\nCHANGE_MONITOR_DIRTY=1"
+                ),
+                "pairs" => format!("'src/main.rs'='{HASH} DIRTY'"),
+                name => panic!("No expected output for format {name}"),
+            };
+            assert_eq!(
+                format.render(&record, &options, false),
+                expected,
+                "{}",
+                format.name
+            );
+        }
+        let tsv = Format::parse("tsv").unwrap();
+        assert_eq!(
+            tsv.header(&record, &options).unwrap(),
+            "file\tcommit\tshort\tdate\tdirty"
+        );
+    }
+
+    /// A value with the special characters of all formats
+    const TRICKY: &str = "a'b $c\"d#e=f:g\\h\ti\r\nj é";
+
+    #[test]
+    fn every_format_escapes_its_special_characters() {
+        let options = options(&["file"]);
+        let record = Record::empty("x y.txt", TRICKY);
+        for format in FORMATS {
+            let expected = match format.name {
+                // Printed as it is
+                "plain" => format!("x y.txt\t{TRICKY}"),
+                "json" => r#""commit": "a'b $c\"d#e=f:g\\h\ti\r\nj é""#.to_string(),
+                "tsv" => r#"x y.txt	a'b $c"d#e=f:g\\h\ti\r\nj é	"#.to_string(),
+                "shell" => "FILE='x y.txt'; COMMIT='a'\\''b $c\"d#e=f:g\\h\ti\r\nj é';".to_string(),
+                "make" => {
+                    "CHANGE_MONITOR_X_Y_TXT_COMMIT := a'b $$c\"d\\#e=f:g\\h\ti  j é\n".to_string()
+                }
+                "stamp" => {
+                    "STABLE_CHANGE_MONITOR_X_Y_TXT_COMMIT a'b $c\"d#e=f:g\\h\ti  j é\n".to_string()
+                }
+                "properties" => {
+                    r#"change.monitor.x\ y.txt.commit=a'b $c"d\#e\=f\:g\\h\ti\r\nj \u00E9"#
+                        .to_string()
+                }
+                "env" => "CHANGE_MONITOR_X_Y_TXT_COMMIT=a'b $c\"d#e=f:g\\h\ti  j é\n".to_string(),
+                "pairs" => "'x y.txt'='a'\\''b $c\"d#e=f:g\\h\ti\r\nj é'".to_string(),
+                name => panic!("No expected output for format {name}"),
+            };
+            let output = format.render(&record, &options, true);
+            assert!(
+                output.contains(&expected),
+                "{}: {output:?} doesn't contain {expected:?}",
+                format.name
+            );
+        }
+    }
+
+    #[test]
+    fn shell_output_evaluates_to_the_values() {
+        let options = options(&["--format=shell", "file"]);
+        let record = Record::empty("it's $(touch x).txt", TRICKY);
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "{}\nprintf '%s\\0' \"$FILE\" \"$COMMIT\"",
+                shell(&record, &options, true)
+            ))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("it's $(touch x).txt\0{TRICKY}\0")
+        );
+    }
+
+    /// Reads `key=value` lines like `java.util.Properties::load`, for the escapes `properties` writes
+    fn load_properties(text: &str) -> Vec<(String, String)> {
+        let unescape = |text: &str| {
+            let mut units = Vec::new();
+            let mut chars = text.chars();
+            while let Some(c) = chars.next() {
+                let c = match c {
+                    '\\' => match chars.next().unwrap() {
+                        't' => '\t',
+                        'n' => '\n',
+                        'r' => '\r',
+                        'f' => '\x0c',
+                        'u' => {
+                            let hex: String = chars.by_ref().take(4).collect();
+                            units.push(u16::from_str_radix(&hex, 16).unwrap());
+                            continue;
+                        }
+                        c => c,
+                    },
+                    c => c,
+                };
+                units.extend(c.encode_utf16(&mut [0; 2]).iter());
+            }
+            String::from_utf16(&units).unwrap()
+        };
+        text.lines()
+            .map(|line| {
+                // The key ends at the first unescaped separator
+                let mut escaped = false;
+                let end = line
+                    .char_indices()
+                    .find(|&(_, c)| {
+                        let separator = !escaped && matches!(c, '=' | ':');
+                        escaped = !escaped && c == '\\';
+                        separator
+                    })
+                    .unwrap()
+                    .0;
+                (unescape(&line[..end]), unescape(&line[end + 1..]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn properties_round_trip_with_an_ambiguous_path() {
+        let options = options(&["--format=properties", "file"]);
+        // Separators, a space and characters outside of ASCII and the BMP in the file
+        let file = "docs/a b=c:d é😀.typ";
+        let record = Record::empty(file, TRICKY);
+        let output = properties(&record, &options, true);
+        assert!(output.is_ascii());
+        assert_eq!(
+            load_properties(&output),
+            [
+                (format!("change.monitor.{file}.commit"), TRICKY.to_string()),
+                (format!("change.monitor.{file}.short"), TRICKY.to_string()),
+                (format!("change.monitor.{file}.date"), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn shell_quotes_an_age_that_is_not_a_number() {
+        let options = options(&["--age-seconds", "--format=shell", "file"]);
+        let mut record = Record::empty("file", "none; echo INJECTED");
+        assert_eq!(
+            shell(&record, &options, false),
+            "COMMIT='none; echo INJECTED'; SHORT='none; echo INJECTED'; DATE=''; \
+             AGE_SECONDS='none; echo INJECTED'"
+        );
+        record.value = "42".to_string();
+        record.dirty = Some(true);
+        assert!(shell(&record, &options, false).ends_with("; DIRTY=1; AGE_SECONDS=42"));
+    }
+
+    #[test]
+    fn template_dirty_is_empty_without_a_dirty_check() {
+        let options = options(&["--clean-marker", "CLEAN", "file"]);
+        let template = Template::parse("[{dirty}]").unwrap();
+        let mut record = Record::empty("file", "none");
+        assert_eq!(template.render(&record, &options), "[]");
+        record.dirty = Some(false);
+        assert_eq!(template.render(&record, &options), "[CLEAN]");
+        record.dirty = Some(true);
+        assert_eq!(template.render(&record, &options), "[DIRTY]");
+    }
+
+    #[test]
+    fn shell_quote_only_escapes_single_quotes() {
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b $HOME `x` \\"), "'a b $HOME `x` \\'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(json_string("a\tb\nc\r"), r#""a\tb\nc\r""#);
+        assert_eq!(json_string("\x01é"), r#""\u0001é""#);
+    }
+
+    #[test]
+    fn properties_escape_keys_and_values() {
+        assert_eq!(properties_escape("a b=c:d", true), r"a\ b\=c\:d");
+        assert_eq!(properties_escape(" a b", false), r"\ a b");
+        assert_eq!(properties_escape("#!\\", false), r"\#\!\\");
+        assert_eq!(properties_escape("a\tb\nc\rd\x0c", false), r"a\tb\nc\rd\f");
+        assert_eq!(properties_escape("é€", false), r"\u00E9\u20AC");
+        // Outside the BMP as a surrogate pair
+        assert_eq!(properties_escape("😀", false), r"\uD83D\uDE00");
+    }
+}
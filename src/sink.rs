@@ -0,0 +1,79 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Writes several files as one unit: every file is first written to a temporary file next to it,
+/// and only once all of them were written, the temporary files are renamed to their targets.
+/// Dropping the transaction without committing removes the temporary files, the targets are
+/// left untouched.
+#[derive(Default)]
+pub struct Transaction {
+    /// Temporary file and target of every staged write, in order
+    staged: Vec<(PathBuf, PathBuf)>,
+    /// The opened files of every staged touch
+    touched: Vec<(File, PathBuf)>,
+}
+
+impl Transaction {
+    /// Stages replacing the file with the content
+    pub fn write(&mut self, target: &Path, content: &[u8]) -> Result<(), String> {
+        let temporary = self.temporary(target)?;
+        fs::write(&temporary, content)
+            .map_err(|e| format!("Failed to write {}: {e}", temporary.display()))
+    }
+
+    /// Stages updating the modification time of the file, creating it if it doesn't exist.
+    /// The file is opened for writing now, so it fails before anything is replaced if it can't
+    /// be touched, but it is neither truncated nor replaced.
+    pub fn touch(&mut self, target: &Path) -> Result<(), String> {
+        if !target.exists() {
+            return self.write(target, b"");
+        }
+        let file = File::options()
+            .write(true)
+            .open(target)
+            .map_err(|e| format!("Failed to open {}: {e}", target.display()))?;
+        self.touched.push((file, target.to_path_buf()));
+        Ok(())
+    }
+
+    /// Renames all temporary files to their targets and updates the modification time of the
+    /// touched files. If a rename fails, the remaining temporary files are removed, but the
+    /// targets renamed before stay replaced.
+    pub fn commit(mut self) -> Result<(), String> {
+        for (temporary, target) in &self.staged {
+            fs::rename(temporary, target)
+                .map_err(|e| format!("Failed to replace {}: {e}", target.display()))?;
+        }
+        self.staged.clear();
+        let now = SystemTime::now();
+        for (file, target) in &self.touched {
+            file.set_modified(now)
+                .map_err(|e| format!("Failed to touch {}: {e}", target.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Registers a new temporary file in the directory of the target, so renaming it is atomic
+    fn temporary(&mut self, target: &Path) -> Result<PathBuf, String> {
+        let name = target
+            .file_name()
+            .ok_or_else(|| format!("Not a file: {}", target.display()))?;
+        let mut temporary_name = std::ffi::OsString::from(".");
+        temporary_name.push(name);
+        temporary_name.push(format!(".{}.tmp", std::process::id()));
+        let temporary = target.with_file_name(temporary_name);
+        self.staged.push((temporary.clone(), target.to_path_buf()));
+        Ok(temporary)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        for (temporary, _) in &self.staged {
+            let _ = fs::remove_file(temporary);
+        }
+    }
+}
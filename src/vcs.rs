@@ -0,0 +1,459 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The latest commit found for a set of monitored files
+#[derive(Clone)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub short: String,
+    /// Date of the person selected by [`Identity`], short format
+    pub date: String,
+    /// Date of the person selected by [`Identity`] as Unix timestamp
+    pub timestamp: i64,
+    /// Name and email of the person selected by [`Identity`]
+    pub name: String,
+    pub email: String,
+}
+
+/// Whose name, email and date are reported for a commit. The author wrote the change, the
+/// committer applied it, so only the author's survive a rebase or cherry-pick.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Identity {
+    Author,
+    /// The default, as the committer date follows the order of the history
+    Committer,
+    /// The author, or the committer if the commit has no author name
+    AuthorOrCommitter,
+}
+
+impl Identity {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "author" => Ok(Identity::Author),
+            "committer" => Ok(Identity::Committer),
+            "author-or-committer" => Ok(Identity::AuthorOrCommitter),
+            _ => Err(format!(
+                "Invalid identity: {value} (expected author, committer or author-or-committer)"
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Identity::Author => "author",
+            Identity::Committer => "committer",
+            Identity::AuthorOrCommitter => "author-or-committer",
+        }
+    }
+}
+
+impl CommitInfo {
+    /// Substitutes `{hash}` and `{short}` in the given template
+    pub fn url(&self, template: &str) -> String {
+        template
+            .replace("{hash}", &self.hash)
+            .replace("{short}", &self.short)
+    }
+
+    /// Seconds between the date and now, 0 if the commit is dated in the future
+    pub fn age_seconds(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before Unix epoch")
+            .as_secs();
+        now.saturating_sub(self.timestamp.max(0) as u64)
+    }
+}
+
+/// Resolves `.` and `..` in the path without accessing the file system, so paths like
+/// `sub/../file` (git lists files relative to the working directory) can be compared
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The size of a commit, like the summary of `git log --shortstat`
+pub struct Diffstat {
+    pub files_changed: u64,
+    pub insertions: u64,
+    pub deletions: u64,
+}
+
+/// A version control system the monitored files are queried from.
+/// Currently only git is implemented, see [`crate::git::Repository`].
+pub trait VersionControl {
+    /// Finds the latest commit affecting the files
+    fn latest_commit(&self, files: &[String]) -> Option<CommitInfo>;
+
+    /// Finds the latest commit affecting the files in the history of the revision
+    fn latest_commit_at(&self, files: &[String], revision: &str) -> Option<CommitInfo>;
+
+    /// Lists up to `count` of the latest commits affecting the files, newest first
+    fn recent_commits(&self, files: &[String], count: usize) -> Vec<CommitInfo>;
+
+    /// Finds the latest commit affecting each group of paths, like `latest_commit` per group but in
+    /// a single walk of the history. The paths are literal files or directories (no wildcards),
+    /// absolute or relative to the working directory.
+    fn latest_commits(&self, groups: &[Vec<PathBuf>]) -> Result<Vec<Option<CommitInfo>>, String> {
+        Ok(self
+            .ranked_latest_commits(groups)?
+            .into_iter()
+            .map(|commit| commit.map(|(_, commit)| commit))
+            .collect())
+    }
+
+    /// Like `latest_commits`, with the position of each commit in the walk (0 is the newest).
+    /// Of the commits of several groups, the one with the lowest position is the latest, which
+    /// the dates can't tell reliably (equal timestamps, or author dates after a rebase).
+    fn ranked_latest_commits(
+        &self,
+        groups: &[Vec<PathBuf>],
+    ) -> Result<Vec<Option<(usize, CommitInfo)>>, String>;
+
+    /// Finds the latest commit of every single file in one walk of the history, see `latest_commits`.
+    /// The map is keyed by the given paths (so it has no order), the value is None for files
+    /// without history, e.g. untracked files.
+    fn latest_commit_for_many(
+        &self,
+        files: &[PathBuf],
+    ) -> Result<HashMap<PathBuf, Option<CommitInfo>>, String> {
+        let groups: Vec<Vec<PathBuf>> = files.iter().map(|file| vec![file.clone()]).collect();
+        let commits = self.latest_commits(&groups)?;
+        Ok(files.iter().cloned().zip(commits).collect())
+    }
+
+    /// Finds the latest commit of any branch that introduced the blob (a content hash) under any path
+    fn latest_commit_with_blob(&self, blob: &str) -> Option<CommitInfo>;
+
+    /// Lists the files with uncommitted changes
+    fn dirty_files(&self, files: &[String]) -> Vec<String>;
+
+    /// Checks each group of paths for uncommitted changes, like `!is_clean` per group but with a
+    /// single query. The paths are literal files or directories, see `latest_commits`.
+    fn dirty_groups(&self, groups: &[Vec<PathBuf>]) -> Result<Vec<bool>, String>;
+
+    /// Checks if the files have no uncommitted changes
+    fn is_clean(&self, files: &[String]) -> bool {
+        self.dirty_files(files).is_empty()
+    }
+
+    /// Hash of the content of the files at the current commit, which does not change on commits not touching them
+    fn tree_hash(&self, files: &[String]) -> Result<String, String>;
+
+    /// Hash of the current content of the files, either in the working tree or, if `staged` is set, in the index
+    fn fingerprint(&self, files: &[String], staged: bool) -> Result<String, String>;
+
+    /// Lists the absolute, normalized paths of all tracked files matching the files, e.g. for
+    /// wildcards or directories
+    fn list_files(&self, files: &[String]) -> Result<Vec<PathBuf>, String>;
+
+    /// The files that are ignored (e.g. by `.gitignore`) and untracked, so they have no history
+    fn ignored_files(&self, files: &[String]) -> Vec<String>;
+
+    /// The blob hash of the file (relative to the repository root) at HEAD, None if it is not committed
+    fn committed_blob(&self, path: &str) -> Option<String>;
+
+    /// The blob hash of the current content of the file (relative to the repository root),
+    /// None if it doesn't exist in the working tree
+    fn content_blob(&self, path: &str) -> Option<String>;
+
+    /// The root directory of the repository
+    fn repo_root(&self) -> Result<PathBuf, String>;
+
+    /// The changes of the commit to the files, compared to its first parent
+    fn diffstat(&self, commit: &str, files: &[String]) -> Result<Diffstat, String>;
+
+    /// The number of commits in the history of `to` that are not in the history of `from`
+    fn commit_count(&self, from: &str, to: &str) -> Result<u64, String>;
+
+    /// The name of the checked out branch, None if HEAD is detached
+    fn current_branch(&self) -> Result<Option<String>, String>;
+
+    /// The full hash of the commit the revision points to
+    fn resolve_commit(&self, revision: &str) -> Result<String, String>;
+
+    /// The first parent of the commit, None for a root commit
+    fn first_parent(&self, commit: &str) -> Result<Option<String>, String>;
+
+    /// The best common ancestor of the commit and the reference, None if they have none
+    fn merge_base(&self, commit: &str, reference: &str) -> Result<Option<String>, String>;
+}
+
+/// An in-memory history for tests of the code built on [`VersionControl`], without running git
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::{CommitInfo, Diffstat, VersionControl};
+    use std::path::{Path, PathBuf};
+
+    /// Commits newest first, each with the files (relative to the root) it changed
+    pub struct MockRepository {
+        pub root: PathBuf,
+        pub commits: Vec<(CommitInfo, Vec<String>)>,
+        /// Files with uncommitted changes
+        pub dirty: Vec<String>,
+    }
+
+    /// A commit with a fake hash derived from the number, dated at the number as timestamp
+    pub fn commit(number: i64) -> CommitInfo {
+        CommitInfo {
+            hash: format!("{number:040x}"),
+            short: format!("{number:07x}"),
+            date: format!("date {number}"),
+            timestamp: number,
+            name: "Mock".to_string(),
+            email: "mock@example.com".to_string(),
+        }
+    }
+
+    impl MockRepository {
+        /// The history of `(commit number, changed files)`, newest first
+        pub fn new(history: &[(i64, &[&str])]) -> Self {
+            MockRepository {
+                root: PathBuf::from("/mock"),
+                commits: history
+                    .iter()
+                    .map(|(number, files)| {
+                        let files = files.iter().map(|file| file.to_string()).collect();
+                        (commit(*number), files)
+                    })
+                    .collect(),
+                dirty: Vec::new(),
+            }
+        }
+
+        /// The path relative to the root, the empty path for the root itself
+        fn relative(&self, path: &Path) -> String {
+            let path = self.root.join(path);
+            let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+            relative.to_string_lossy().trim_end_matches('/').to_string()
+        }
+
+        /// Whether the file is the path or inside it
+        fn matches(&self, path: &str, file: &str) -> bool {
+            let path = self.relative(Path::new(path));
+            path.is_empty()
+                || file
+                    .strip_prefix(&path)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        }
+
+        fn ranked(&self, files: &[String]) -> Option<(usize, CommitInfo)> {
+            self.commits
+                .iter()
+                .enumerate()
+                .find(|(_, (_, changed))| {
+                    changed
+                        .iter()
+                        .any(|file| files.iter().any(|path| self.matches(path, file)))
+                })
+                .map(|(rank, (commit, _))| (rank, commit.clone()))
+        }
+
+        fn strings(paths: &[PathBuf]) -> Vec<String> {
+            paths
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect()
+        }
+    }
+
+    impl VersionControl for MockRepository {
+        fn latest_commit(&self, files: &[String]) -> Option<CommitInfo> {
+            self.ranked(files).map(|(_, commit)| commit)
+        }
+
+        fn latest_commit_at(&self, files: &[String], _revision: &str) -> Option<CommitInfo> {
+            self.latest_commit(files)
+        }
+
+        fn recent_commits(&self, files: &[String], count: usize) -> Vec<CommitInfo> {
+            self.commits
+                .iter()
+                .filter(|(_, changed)| {
+                    changed
+                        .iter()
+                        .any(|file| files.iter().any(|path| self.matches(path, file)))
+                })
+                .take(count)
+                .map(|(commit, _)| commit.clone())
+                .collect()
+        }
+
+        fn ranked_latest_commits(
+            &self,
+            groups: &[Vec<PathBuf>],
+        ) -> Result<Vec<Option<(usize, CommitInfo)>>, String> {
+            Ok(groups
+                .iter()
+                .map(|group| self.ranked(&Self::strings(group)))
+                .collect())
+        }
+
+        fn latest_commit_with_blob(&self, _blob: &str) -> Option<CommitInfo> {
+            None
+        }
+
+        fn dirty_files(&self, files: &[String]) -> Vec<String> {
+            self.dirty
+                .iter()
+                .filter(|file| files.iter().any(|path| self.matches(path, file)))
+                .cloned()
+                .collect()
+        }
+
+        fn dirty_groups(&self, groups: &[Vec<PathBuf>]) -> Result<Vec<bool>, String> {
+            Ok(groups
+                .iter()
+                .map(|group| !self.is_clean(&Self::strings(group)))
+                .collect())
+        }
+
+        fn tree_hash(&self, _files: &[String]) -> Result<String, String> {
+            Err("Not supported by the mock".to_string())
+        }
+
+        fn fingerprint(&self, _files: &[String], _staged: bool) -> Result<String, String> {
+            Err("Not supported by the mock".to_string())
+        }
+
+        fn list_files(&self, files: &[String]) -> Result<Vec<PathBuf>, String> {
+            let mut listed: Vec<PathBuf> = Vec::new();
+            for (_, changed) in &self.commits {
+                for file in changed {
+                    let path = self.root.join(file);
+                    if files.iter().any(|pattern| self.matches(pattern, file))
+                        && !listed.contains(&path)
+                    {
+                        listed.push(path);
+                    }
+                }
+            }
+            Ok(listed)
+        }
+
+        fn ignored_files(&self, _files: &[String]) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn committed_blob(&self, _path: &str) -> Option<String> {
+            None
+        }
+
+        fn content_blob(&self, _path: &str) -> Option<String> {
+            None
+        }
+
+        fn repo_root(&self) -> Result<PathBuf, String> {
+            Ok(self.root.clone())
+        }
+
+        fn diffstat(&self, _commit: &str, _files: &[String]) -> Result<Diffstat, String> {
+            Err("Not supported by the mock".to_string())
+        }
+
+        fn commit_count(&self, from: &str, to: &str) -> Result<u64, String> {
+            let position = |hash: &str| {
+                self.commits
+                    .iter()
+                    .position(|(commit, _)| commit.hash == hash)
+                    .ok_or_else(|| format!("Unknown commit {hash}"))
+            };
+            Ok(position(from)?.saturating_sub(position(to)?) as u64)
+        }
+
+        fn current_branch(&self) -> Result<Option<String>, String> {
+            Ok(Some("main".to_string()))
+        }
+
+        fn resolve_commit(&self, _revision: &str) -> Result<String, String> {
+            self.commits
+                .first()
+                .map(|(commit, _)| commit.hash.clone())
+                .ok_or_else(|| "No commits".to_string())
+        }
+
+        fn first_parent(&self, commit: &str) -> Result<Option<String>, String> {
+            let position = self
+                .commits
+                .iter()
+                .position(|(info, _)| info.hash == commit)
+                .ok_or_else(|| format!("Unknown commit {commit}"))?;
+            Ok(self
+                .commits
+                .get(position + 1)
+                .map(|(parent, _)| parent.hash.clone()))
+        }
+
+        fn merge_base(&self, commit: &str, _reference: &str) -> Result<Option<String>, String> {
+            Ok(Some(commit.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{commit, MockRepository};
+    use super::*;
+
+    fn history() -> MockRepository {
+        MockRepository::new(&[
+            (3, &["docs/b.typ"]),
+            (2, &["a.typ", "shared.typ"]),
+            (1, &["a.typ", "docs/b.typ"]),
+        ])
+    }
+
+    #[test]
+    fn latest_commits_strips_the_ranks() {
+        let repository = history();
+        let groups = vec![
+            vec![PathBuf::from("a.typ")],
+            vec![PathBuf::from("docs")],
+            vec![PathBuf::from("missing.typ")],
+        ];
+        let ranked = repository.ranked_latest_commits(&groups).unwrap();
+        assert_eq!(ranked[0].as_ref().map(|(rank, _)| *rank), Some(1));
+        let commits = repository.latest_commits(&groups).unwrap();
+        let hashes: Vec<Option<String>> = commits
+            .into_iter()
+            .map(|commit| commit.map(|commit| commit.hash))
+            .collect();
+        assert_eq!(
+            hashes,
+            vec![Some(commit(2).hash), Some(commit(3).hash), None]
+        );
+    }
+
+    #[test]
+    fn latest_commit_for_many_is_keyed_by_path() {
+        let repository = history();
+        let files = [PathBuf::from("shared.typ"), PathBuf::from("untracked.typ")];
+        let commits = repository.latest_commit_for_many(&files).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(
+            commits[&files[0]].as_ref().map(|commit| commit.timestamp),
+            Some(2)
+        );
+        assert!(commits[&files[1]].is_none());
+    }
+
+    #[test]
+    fn is_clean_uses_dirty_files() {
+        let mut repository = history();
+        repository.dirty = vec!["docs/b.typ".to_string()];
+        assert!(repository.is_clean(&["a.typ".to_string()]));
+        assert!(!repository.is_clean(&["docs".to_string()]));
+    }
+}
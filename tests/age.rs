@@ -0,0 +1,136 @@
+mod common;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use common::Fixture;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[test]
+fn the_age_of_a_recent_commit_is_a_small_number() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    // Not dirty, as the marker is not appended to the age
+    fixture.write("a.txt", "uncommitted");
+
+    let output = fixture.stdout("", &["a.txt", "--age-seconds"]);
+    let age: u64 = output.trim_end().parse().unwrap();
+    assert!(age < 60, "{age}");
+}
+
+#[test]
+fn the_age_is_measured_from_the_date_of_the_identity() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.git(&["add", "a.txt"]);
+    let date = format!("@{} +0000", now() - 2 * 86400 - 3600);
+    fixture.git(&["commit", "--quiet", "--message", "old", "--date", &date]);
+
+    let output = fixture.stdout("", &["a.txt", "--age-seconds", "--identity", "author"]);
+    let age: u64 = output.trim_end().parse().unwrap();
+    assert!((2 * 86400 + 3600..2 * 86400 + 3660).contains(&age), "{age}");
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "--age-seconds",
+            "--identity",
+            "author",
+            "--age-format",
+            "days",
+        ],
+    );
+    assert_eq!(output, "2 days\n");
+
+    // The committer date is now
+    let output = fixture.stdout("", &["a.txt", "--age-seconds"]);
+    let age: u64 = output.trim_end().parse().unwrap();
+    assert!(age < 60, "{age}");
+}
+
+#[test]
+fn the_age_is_zero_padded() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    let output = fixture.stdout("", &["a.txt", "--age-seconds", "--number-format", "%06d"]);
+    let age = output.trim_end();
+    assert_eq!(age.len(), 6, "{age}");
+    assert!(age.starts_with("0000"), "{age}");
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "--age-seconds",
+            "--age-format",
+            "days",
+            "--number-format",
+            "%03d",
+        ],
+    );
+    assert_eq!(output, "000 days\n");
+}
+
+#[test]
+fn the_age_is_rounded_down_to_the_unit() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.git(&["add", "a.txt"]);
+    let date = format!("@{} +0000", now() - 8 * 86400 - 3 * 3600 - 5 * 60);
+    fixture.git(&["commit", "--quiet", "--message", "old", "--date", &date]);
+
+    let age = |unit: &str| {
+        fixture.stdout(
+            "",
+            &[
+                "a.txt",
+                "--age-seconds",
+                "--identity",
+                "author",
+                "--age-format",
+                unit,
+            ],
+        )
+    };
+    assert_eq!(age("minutes"), "11705 minutes\n");
+    assert_eq!(age("hours"), "195 hours\n");
+    assert_eq!(age("days"), "8 days\n");
+    assert_eq!(age("weeks"), "1 week\n");
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "--age-seconds",
+            "--identity",
+            "author",
+            "--age-format",
+            "weeks",
+            "--format",
+            "json",
+        ],
+    );
+    assert!(output.contains("\"age\": \"1 week\""), "{output}");
+}
+
+#[test]
+fn the_unit_requires_the_age() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    for args in [
+        &["a.txt", "--age-format", "days"][..],
+        &["a.txt", "--age-seconds", "--age-format", "months"],
+    ] {
+        let output = fixture.run("", args);
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+    }
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::Fixture;
+
+/// `old/name.txt` renamed to `name.txt`, with a commit to the old path before the rename.
+/// Returns the hash of that commit and of the rename.
+fn fixture(manifest: &str) -> (Fixture, String, String) {
+    let fixture = Fixture::new();
+    fixture.write("old/name.txt", "v1");
+    fixture.write("other.txt", "other");
+    fixture.commit("initial");
+    fixture.write("old/name.txt", "v2");
+    let before_rename = fixture.commit("change under the old path");
+    fixture.write("other.txt", "changed");
+    fixture.commit("unrelated");
+    fixture.git(&["mv", "old/name.txt", "name.txt"]);
+    fixture.write(".deps.toml", manifest);
+    let rename = fixture.commit("rename");
+    (fixture, before_rename, rename)
+}
+
+#[test]
+fn aliases_are_part_of_the_history_query() {
+    let (fixture, before_rename, _) =
+        fixture("[\"name.txt\"]\ndependencies = []\naliases = [\"old/name.txt\"]\n");
+
+    let output = fixture.stdout("", &["name.txt", "--ref", "HEAD~1"]);
+    // Before the rename, only the alias existed
+    assert_eq!(output, format!("{before_rename}\n"));
+    let output = fixture.stdout("", &["name.txt", "--print-pathspecs"]);
+    assert_eq!(output, "'name.txt' 'old/name.txt'\n");
+}
+
+#[test]
+fn without_the_alias_the_earlier_history_is_missing() {
+    let (fixture, _, _) = fixture("[\"name.txt\"]\ndependencies = []\n");
+
+    let output = fixture.run("", &["name.txt", "--ref", "HEAD~1"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No commits found"), "{stderr}");
+}
+
+#[test]
+fn aliases_are_not_part_of_the_dirty_check() {
+    let (fixture, _, rename) =
+        fixture("[\"name.txt\"]\ndependencies = []\naliases = [\"old/name.txt\"]\n");
+    fixture.write("old/name.txt", "reused");
+
+    let output = fixture.stdout("", &["name.txt"]);
+    assert_eq!(output, format!("{rename}\n"));
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::Fixture;
+
+/// `src/main.c` includes `util.h`, which includes `inner/deep.h`, which includes `util.h` again.
+/// `other.c` is not included. Returns the hash of the initial commit.
+#[cfg(feature = "auto-deps")]
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(
+        "src/main.c",
+        "#include <stdio.h>\n#include \"util.h\"\n#include \"missing.h\"\n",
+    );
+    fixture.write("src/util.h", "# include \"inner/deep.h\"\n");
+    fixture.write("src/inner/deep.h", "#include \"../util.h\"\n");
+    fixture.write("src/other.c", "int other;\n");
+    let initial = fixture.commit("initial");
+    (fixture, initial)
+}
+
+#[cfg(feature = "auto-deps")]
+#[test]
+fn the_includes_are_followed_transitively() {
+    let (fixture, _) = fixture();
+    let output = fixture.stdout("", &["src/main.c", "--auto-deps", "c", "--dump-resolved"]);
+    assert_eq!(
+        output.trim_end(),
+        "{ \"main.c\": { \"files\": [\"main.c\", \"util.h\", \"inner/deep.h\"], \
+         \"aliases\": [], \"from_fallback\": false } }"
+    );
+
+    fixture.write("src/inner/deep.h", "#include \"../util.h\"\nint deep;\n");
+    let deep = fixture.commit("change the nested include");
+    fixture.write("src/other.c", "int changed;\n");
+    fixture.commit("change a file that is not included");
+
+    let output = fixture.run("", &["src/main.c", "--auto-deps", "c"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("WARN"));
+    assert_eq!(common::stdout(output), format!("{deep}\n"));
+}
+
+#[cfg(feature = "auto-deps")]
+#[test]
+fn the_manifest_wins() {
+    let (fixture, initial) = fixture();
+    fixture.write("src/.deps.toml", "[\"main.c\"]\ndependencies = []\n");
+    fixture.commit("add the manifest");
+    fixture.write("src/util.h", "int util;\n");
+    fixture.commit("change the include");
+
+    let output = fixture.stdout("", &["src/main.c", "--auto-deps", "c"]);
+    assert_eq!(output, format!("{initial}\n"));
+}
+
+#[cfg(not(feature = "auto-deps"))]
+#[test]
+fn the_option_requires_the_feature() {
+    let fixture = Fixture::new();
+    fixture.write("main.c", "");
+    fixture.commit("initial");
+
+    let output = fixture.run("", &["main.c", "--auto-deps", "c"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("requires the auto-deps feature"));
+}
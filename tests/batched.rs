@@ -0,0 +1,116 @@
+mod common;
+
+use common::Fixture;
+use std::fs;
+
+/// A run with several files answers from a single walk, which has to match the queries per file
+fn assert_batched_matches_single(fixture: &Fixture, directory: &str, files: &[&str]) {
+    let batched = fixture.stdout(directory, files);
+    let single: Vec<String> = files
+        .iter()
+        .map(|file| fixture.stdout(directory, &[file]).trim_end().to_string())
+        .collect();
+    let batched: Vec<&str> = batched
+        .lines()
+        .map(|line| line.split_once('\t').map_or(line, |(_, result)| result))
+        .collect();
+    assert_eq!(batched, single);
+}
+
+#[test]
+fn dependencies_in_parent_directories() {
+    let fixture = Fixture::new();
+    fixture.write("sub/b.txt", "b");
+    fixture.write("sub/c.txt", "c");
+    fixture.write(
+        "sub/.deps.toml",
+        "[\"b.txt\"]\ndependencies = [\"../a.txt\"]\n\n[\"c.txt\"]\ndependencies = []\n",
+    );
+    fixture.commit("b and c");
+    fixture.write("a.txt", "a");
+    let a = fixture.commit("a");
+
+    assert_batched_matches_single(&fixture, "sub", &["b.txt", "c.txt"]);
+    assert!(fixture.stdout("sub", &["b.txt", "c.txt"]).contains(&a));
+}
+
+#[test]
+fn one_dirty_check_for_files_in_different_directories() {
+    let fixture = Fixture::new();
+    fixture.write("sub/b.txt", "b");
+    fixture.write("sub/.deps.toml", "[\"b.txt\"]\ndependencies = []\n");
+    fixture.write("c.txt", "c");
+    fixture.write(".deps.toml", "[\"c.txt\"]\ndependencies = []\n");
+    fixture.commit("initial");
+    fixture.write("sub/b.txt", "changed");
+
+    // The git commands are logged to the trace file
+    let trace_file = fixture.path(".git/trace");
+    for files in [["sub/b.txt", "c.txt"], ["c.txt", "sub/b.txt"]] {
+        let output = common::stdout(fixture.run_with("", &files, |command| {
+            command.env("GIT_TRACE", &trace_file);
+        }));
+        let trace = fs::read_to_string(&trace_file).unwrap();
+        fs::remove_file(&trace_file).unwrap();
+        assert_eq!(trace.matches("built-in: git status").count(), 1, "{trace}");
+        for line in output.lines() {
+            if line.starts_with("sub/b.txt\t") {
+                assert!(line.ends_with(" DIRTY"), "{output}");
+            } else {
+                assert!(!line.ends_with(" DIRTY"), "{output}");
+            }
+        }
+        assert_eq!(output.lines().count(), 2, "{output}");
+    }
+}
+
+#[test]
+fn dependencies_with_spaces_and_quotes_are_dirty() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    fixture.write("sp ace.txt", "c");
+    fixture.write("q\"uote.txt", "d");
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = [\"sp ace.txt\"]\n\n[\"b.txt\"]\ndependencies = ['q\"uote.txt']\n",
+    );
+    fixture.commit("initial");
+    fixture.write("sp ace.txt", "changed");
+    fixture.write("q\"uote.txt", "changed");
+
+    let assert_dirty = |args: &[&str]| {
+        let mut args = args.to_vec();
+        args.extend(["a.txt", "b.txt"]);
+        let output = fixture.stdout("", &args);
+        assert_eq!(output.lines().count(), 2, "{output}");
+        assert!(
+            output.lines().all(|line| line.ends_with(" DIRTY")),
+            "{args:?}: {output}"
+        );
+    };
+    assert_dirty(&["--status-format", "v1"]);
+    assert_dirty(&["--status-format", "v2"]);
+
+    // Only staged changes, listed by git diff --cached
+    fixture.git(&["add", "--all"]);
+    assert_dirty(&["--staged-only"]);
+}
+
+#[test]
+fn a_path_outside_of_the_repository_does_not_fail_the_other_files() {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = [\"../../outside.txt\"]\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    let head = fixture.commit("initial");
+    fixture.write("a.txt", "changed");
+
+    let output = fixture.stdout("", &["a.txt", "b.txt", "--empty-value", "NONE"]);
+    assert_eq!(output, format!("a.txt\t{head} DIRTY\nb.txt\tNONE\n"));
+    let output = fixture.stdout("", &["b.txt", "a.txt", "--empty-value", "NONE"]);
+    assert_eq!(output, format!("b.txt\tNONE\na.txt\t{head} DIRTY\n"));
+}
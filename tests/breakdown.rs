@@ -0,0 +1,59 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` depends on `dep.txt`, which was changed after `a.txt`.
+/// Returns the hashes of the commits of `a.txt` and of `dep.txt`.
+fn fixture() -> (Fixture, String, String) {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n\n[\"b.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    fixture.write("dep.txt", "dep");
+    let file = fixture.commit("initial");
+    fixture.write("dep.txt", "changed");
+    let dependency = fixture.commit("change the dependency");
+    (fixture, file, dependency)
+}
+
+#[test]
+fn a_newer_dependency_is_reported_separately() {
+    let (fixture, file, dependency) = fixture();
+
+    let output = fixture.stdout("", &["a.txt", "--breakdown"]);
+    assert_eq!(output, format!("{dependency} {file} {dependency}\n"));
+
+    let output = fixture.stdout("", &["a.txt", "--breakdown", "--format", "json"]);
+    assert!(
+        output.contains(&format!("\"commit\": \"{dependency}\"")),
+        "{output}"
+    );
+    assert!(
+        output.contains(&format!(
+            "\"file_commit\": \"{file}\", \"deps_commit\": \"{dependency}\""
+        )),
+        "{output}"
+    );
+}
+
+#[test]
+fn a_file_without_dependencies_has_no_dependency_commit() {
+    let (fixture, file, _) = fixture();
+
+    let output = fixture.stdout("", &["b.txt", "--breakdown"]);
+    assert_eq!(output, format!("{file} {file} -\n"));
+    let output = fixture.stdout("", &["b.txt", "--breakdown", "--format", "json"]);
+    assert!(output.contains("\"deps_commit\": null"), "{output}");
+}
+
+#[test]
+fn only_the_hash_can_be_broken_down() {
+    let (fixture, _, _) = fixture();
+
+    let output = fixture.run("", &["a.txt", "--breakdown", "--date"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--breakdown cannot be combined"));
+}
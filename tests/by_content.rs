@@ -0,0 +1,72 @@
+mod common;
+
+use common::Fixture;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "content");
+    fixture
+}
+
+#[test]
+fn the_latest_commit_of_the_content_under_any_path() {
+    let fixture = fixture();
+    let added = fixture.commit("add a.txt");
+    fixture.write("other.txt", "other");
+    fixture.commit("unrelated");
+
+    // The same content appears under another path on another branch
+    fixture.git(&["checkout", "--quiet", "-b", "copy"]);
+    fixture.write("elsewhere/b.txt", "content");
+    let copied = fixture.commit("copy the content");
+    fixture.git(&["checkout", "--quiet", "main"]);
+
+    assert_eq!(fixture.stdout("", &["a.txt"]), format!("{added}\n"));
+    assert_eq!(
+        fixture.stdout("", &["a.txt", "--by-content"]),
+        format!("{copied}\n")
+    );
+}
+
+#[test]
+fn the_latest_commit_introducing_the_current_content() {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("old/a.txt", "content");
+    fixture.commit("add under the old path");
+    fixture.git(&["mv", "old/a.txt", "a.txt"]);
+    let moved = fixture.commit("move to a.txt");
+    assert_eq!(
+        fixture.stdout("", &["a.txt", "--by-content"]),
+        format!("{moved}\n")
+    );
+
+    fixture.write("a.txt", "changed");
+    let changed = fixture.commit("change");
+    fixture.write("a.txt", "content");
+    let reverted = fixture.commit("change back");
+    assert_eq!(
+        fixture.stdout("", &["a.txt", "--by-content"]),
+        format!("{reverted}\n")
+    );
+
+    // Uncommitted, but committed before
+    fixture.write("a.txt", "changed");
+    assert_eq!(
+        fixture.stdout("", &["a.txt", "--by-content"]),
+        format!("{changed} DIRTY\n")
+    );
+}
+
+#[test]
+fn uncommitted_content_has_no_commit() {
+    let fixture = fixture();
+    fixture.commit("add a.txt");
+    fixture.write("a.txt", "never committed");
+
+    let output = fixture.run("", &["a.txt", "--by-content"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No commits found"), "{stderr}");
+}
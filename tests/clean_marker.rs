@@ -0,0 +1,38 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn the_clean_marker_is_appended_to_a_clean_hash() {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    let hash = fixture.commit("initial");
+    fixture.write("b.txt", "uncommitted");
+
+    let output = fixture.stdout("", &["a.txt", "b.txt", "--clean-marker", "CLEAN"]);
+    assert_eq!(
+        output,
+        format!("a.txt\t{hash} CLEAN\nb.txt\t{hash} DIRTY\n")
+    );
+    // Disabled by default and by an empty string
+    let output = fixture.stdout("", &["a.txt"]);
+    assert_eq!(output, format!("{hash}\n"));
+    let output = fixture.stdout("", &["a.txt", "--clean-marker", ""]);
+    assert_eq!(output, format!("{hash}\n"));
+}
+
+#[test]
+fn the_clean_marker_is_not_appended_to_a_date() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    let date = fixture.git(&["log", "-1", "--format=%cs"]);
+
+    let output = fixture.stdout("", &["a.txt", "--date", "--clean-marker", "CLEAN"]);
+    assert_eq!(output, format!("{date}\n"));
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` is changed, then three commits later `b.txt`
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = []\n\n[\"c.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("b.txt", "b");
+    fixture.write("c.txt", "c");
+    fixture.commit("initial");
+    fixture.write("a.txt", "a");
+    fixture.commit("add a");
+    fixture.write("other.txt", "1");
+    fixture.commit("unrelated");
+    fixture.write("other.txt", "2");
+    fixture.commit("unrelated");
+    fixture.write("b.txt", "changed");
+    fixture.commit("change b");
+    fixture
+}
+
+#[test]
+fn the_commits_between_the_files_are_counted() {
+    let fixture = fixture();
+
+    let output = fixture.run("", &["a.txt", "b.txt", "--commit-delta"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("WARN"));
+    assert_eq!(common::stdout(output), "3\n");
+    let output = fixture.stdout("", &["b.txt", "a.txt", "--commit-delta"]);
+    assert_eq!(output, "-3\n");
+    let output = fixture.stdout("", &["a.txt", "a.txt", "--commit-delta"]);
+    assert_eq!(output, "0\n");
+}
+
+#[test]
+fn unrelated_commits_are_counted_with_a_warning() {
+    let fixture = fixture();
+    fixture.git(&["checkout", "--quiet", "-b", "side", "HEAD~4"]);
+    fixture.write("c.txt", "side");
+    fixture.commit("change c on the side");
+    fixture.git(&["checkout", "--quiet", "main"]);
+    fixture.git(&["merge", "--quiet", "--no-edit", "side"]);
+
+    // The history of b.txt has four commits that are not in the history of c.txt
+    let output = fixture.run("", &["c.txt", "b.txt", "--commit-delta"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("WARN"));
+    assert_eq!(common::stdout(output), "4\n");
+}
+
+#[test]
+fn exactly_two_files_are_compared() {
+    let fixture = fixture();
+
+    let output = fixture.run("", &["a.txt", "--commit-delta"]);
+    assert_eq!(output.status.code(), Some(1));
+}
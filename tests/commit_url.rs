@@ -0,0 +1,49 @@
+mod common;
+
+use common::Fixture;
+
+fn fixture() -> (Fixture, String, String) {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    let hash = fixture.commit("initial");
+    let short = fixture.git(&["rev-parse", "--short", "HEAD"]);
+    (fixture, hash, short)
+}
+
+#[test]
+fn full_and_short_hashes_are_substituted() {
+    let (fixture, hash, short) = fixture();
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "--commit-url-template",
+            "https://example.com/{short}/commit/{hash}",
+        ],
+    );
+    assert_eq!(
+        output,
+        format!("{hash} https://example.com/{short}/commit/{hash}\n")
+    );
+}
+
+#[test]
+fn the_url_is_a_json_field() {
+    let (fixture, hash, _) = fixture();
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "--format",
+            "json",
+            "--commit-url-template",
+            "https://example.com/commit/{hash}",
+        ],
+    );
+    assert!(
+        output.contains(&format!("\"url\": \"https://example.com/commit/{hash}\"")),
+        "{output}"
+    );
+}
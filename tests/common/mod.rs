@@ -0,0 +1,174 @@
+//! A temporary git repository for the tests, removed when dropped
+// Each test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    process::{Command, Output},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Fixture {
+    pub root: PathBuf,
+}
+
+impl Fixture {
+    /// An empty repository with the branch `main`, isolated from the user's git config
+    pub fn new() -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "change-monitor-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        // Canonical, as the tool reports canonical paths (e.g. /tmp may be a symlink)
+        let fixture = Fixture {
+            root: root.canonicalize().unwrap(),
+        };
+        fixture.git(&["init", "--quiet", "--initial-branch=main"]);
+        fixture
+    }
+
+    /// Writes the file (relative to the root), creating its directories
+    pub fn write(&self, path: &str, content: impl AsRef<[u8]>) {
+        let path = self.root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Runs git in the root and returns its trimmed stdout, panics if it fails
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = isolated(Command::new("git"))
+            .current_dir(&self.root)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string()
+    }
+
+    /// Commits all changes and returns the hash of the commit
+    pub fn commit(&self, message: &str) -> String {
+        self.git(&["add", "--all"]);
+        self.git(&["commit", "--quiet", "--allow-empty", "--message", message]);
+        self.git(&["rev-parse", "HEAD"])
+    }
+
+    /// Runs the tool in the directory (relative to the root)
+    pub fn run(&self, directory: &str, args: &[&str]) -> Output {
+        self.run_with(directory, args, |_| {})
+    }
+
+    /// Runs the tool in the directory (relative to the root), after adjusting the command
+    pub fn run_with(
+        &self,
+        directory: &str,
+        args: &[&str],
+        adjust: impl FnOnce(&mut Command),
+    ) -> Output {
+        let mut command = isolated(Command::new(env!("CARGO_BIN_EXE_change-monitor")));
+        command.current_dir(self.root.join(directory)).args(args);
+        adjust(&mut command);
+        command.output().unwrap()
+    }
+
+    /// Runs the tool like `run`, with a git wrapper that logs the arguments of every git command.
+    /// Returns the output and the arguments of each git command, space separated.
+    pub fn run_logging_git(&self, directory: &str, args: &[&str]) -> (Output, Vec<String>) {
+        self.run_logging_git_with(directory, args, |_| {})
+    }
+
+    /// Like `run_logging_git`, after adjusting the command
+    pub fn run_logging_git_with(
+        &self,
+        directory: &str,
+        args: &[&str],
+        adjust: impl FnOnce(&mut Command),
+    ) -> (Output, Vec<String>) {
+        let bin = self.path(".git/logging-bin");
+        let log = self.path(".git/git-commands.log");
+        let _ = fs::remove_file(&log);
+        if !bin.exists() {
+            let git = Command::new("sh")
+                .args(["-c", "command -v git"])
+                .output()
+                .unwrap();
+            let git = String::from_utf8_lossy(&git.stdout).trim().to_string();
+            fs::create_dir_all(&bin).unwrap();
+            let script = bin.join("git");
+            fs::write(
+                &script,
+                format!(
+                    "#!/bin/sh\nprintf '%s\\n' \"$*\" >> '{}'\nexec '{git}' \"$@\"\n",
+                    log.display()
+                ),
+            )
+            .unwrap();
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let path = format!(
+            "{}:{}",
+            bin.display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+        let output = self.run_with(directory, args, |command| {
+            command.env("PATH", path);
+            adjust(command);
+        });
+        let commands = fs::read_to_string(&log)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        (output, commands)
+    }
+
+    /// The stdout of a successful run of the tool, panics if it fails
+    pub fn stdout(&self, directory: &str, args: &[&str]) -> String {
+        stdout(self.run(directory, args))
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// The stdout of a successful run, panics with the stderr otherwise
+pub fn stdout(output: Output) -> String {
+    assert!(
+        output.status.success(),
+        "change-monitor failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Without the user's and the system's git config, and with a fixed identity
+fn isolated(mut command: Command) -> Command {
+    command
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_AUTHOR_NAME", "Test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .env_remove("CI");
+    command
+}
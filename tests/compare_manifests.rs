@@ -0,0 +1,118 @@
+mod common;
+
+use common::Fixture;
+
+const OLD: &str = r#"
+["file1.typ"]
+dependencies = ["dep1.typ", "dep2.typ"]
+
+["file2.typ"]
+dependencies = []
+
+["file4.typ"]
+
+["file5.typ"]
+dependencies = ["dep5.typ"]
+"#;
+
+// file5.typ moves to the path-keyed layout without a change
+const NEW: &str = r#"
+["file1.typ"]
+dependencies = ["dep2.typ", "dep3.typ"]
+aliases = ["docs/renamed.typ"]
+dirty = false
+
+["file3.typ"]
+dependencies = []
+
+["file4.typ"]
+dependencies = ["dep4.typ"]
+
+[[files]]
+path = "file5.typ"
+dependencies = ["dep5.typ"]
+"#;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write("old.deps.toml", OLD);
+    fixture.write("new.deps.toml", NEW);
+    fixture
+}
+
+#[test]
+fn added_removed_and_changed_entries() {
+    let fixture = fixture();
+
+    let output = fixture.stdout(
+        "",
+        &["--compare-manifests", "old.deps.toml", "new.deps.toml"],
+    );
+    assert_eq!(
+        output,
+        "+ file3.typ\n\
+         - file2.typ\n\
+         ~ file1.typ\n  \
+           + dependency dep3.typ\n  \
+           - dependency dep1.typ\n  \
+           + alias docs/renamed.typ\n  \
+           ~ dirty = false\n\
+         ~ file4.typ\n  \
+           + dependency dep4.typ\n  \
+           ~ fallback = false\n"
+    );
+
+    // Removing the dependencies key is the other way around
+    let output = fixture.stdout(
+        "",
+        &["--compare-manifests", "new.deps.toml", "old.deps.toml"],
+    );
+    assert!(
+        output.contains("~ file4.typ\n  - dependency dep4.typ\n  ~ fallback = true\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn json_diff() {
+    let fixture = fixture();
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "--compare-manifests",
+            "old.deps.toml",
+            "new.deps.toml",
+            "--format",
+            "json",
+        ],
+    );
+    assert_eq!(
+        output.trim_end(),
+        r#"{ "added": ["file3.typ"], "removed": ["file2.typ"], "changed": [{ "key": "file1.typ", "added_dependencies": ["dep3.typ"], "removed_dependencies": ["dep1.typ"], "added_aliases": ["docs/renamed.typ"], "removed_aliases": [], "dirty": false, "fallback": null }, { "key": "file4.typ", "added_dependencies": ["dep4.typ"], "removed_dependencies": [], "added_aliases": [], "removed_aliases": [], "dirty": null, "fallback": false }] }"#
+    );
+}
+
+#[test]
+fn equivalent_files_print_nothing() {
+    let fixture = fixture();
+
+    let output = fixture.stdout(
+        "",
+        &["--compare-manifests", "new.deps.toml", "new.deps.toml"],
+    );
+    assert_eq!(output, "");
+}
+
+#[test]
+fn a_missing_file_is_an_error() {
+    let fixture = fixture();
+
+    let output = fixture.run(
+        "",
+        &["--compare-manifests", "old.deps.toml", "missing.toml"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing.toml does not exist"), "{stderr}");
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use change_monitor::config::Config;
+use common::Fixture;
+
+/// `a.txt` depends on `dep.txt`, `b.txt` has no entry and `sub/c.txt` has an entry in the
+/// dependencies file of its own directory. Returns the hashes of the two commits.
+fn fixture() -> (Fixture, String, String) {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n");
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    fixture.write("dep.txt", "dep");
+    fixture.write("sub/.deps.toml", "[\"c.txt\"]\ndependencies = []\n");
+    fixture.write("sub/c.txt", "c");
+    let first = fixture.commit("initial");
+    fixture.write("dep.txt", "changed");
+    let second = fixture.commit("change the dependency");
+    (fixture, first, second)
+}
+
+#[test]
+fn query_uses_the_entry_of_the_file() {
+    let (fixture, _, second) = fixture();
+
+    let query = Config::default().query(&fixture.path("a.txt")).unwrap();
+    assert_eq!(query.resolved.files, ["a.txt", "dep.txt"]);
+    assert!(!query.resolved.from_fallback);
+    assert_eq!(query.commit.unwrap().hash, second);
+    assert_eq!(query.dirty, Some(false));
+
+    fixture.write("dep.txt", "uncommitted");
+    let query = Config::default().query(&fixture.path("a.txt")).unwrap();
+    assert_eq!(query.dirty, Some(true));
+}
+
+#[test]
+fn query_without_an_entry_monitors_the_directory() {
+    let (fixture, _, second) = fixture();
+
+    let query = Config::default().query(&fixture.path("b.txt")).unwrap();
+    assert!(query.resolved.from_fallback);
+    assert_eq!(
+        query.resolved.files,
+        [fixture.root.to_str().unwrap().to_string()]
+    );
+    assert_eq!(query.commit.unwrap().hash, second);
+}
+
+#[test]
+fn query_uses_the_dependencies_file_of_a_nested_directory() {
+    let (fixture, first, _) = fixture();
+
+    let query = Config::default().query(&fixture.path("sub/c.txt")).unwrap();
+    assert_eq!(query.resolved.files, ["c.txt"]);
+    assert!(!query.resolved.from_fallback);
+    assert_eq!(query.commit.unwrap().hash, first);
+}
+
+#[test]
+fn query_applies_the_typed_options() {
+    let (fixture, first, _) = fixture();
+    fixture.write("dep.txt", "uncommitted");
+
+    let config = Config {
+        revision: Some(first.clone()),
+        dirty_check: false,
+        ..Config::default()
+    };
+    let query = config.query(&fixture.path("a.txt")).unwrap();
+    assert_eq!(query.commit.unwrap().hash, first);
+    assert_eq!(query.dirty, None);
+}
+
+#[test]
+fn query_reports_an_entry_of_the_wrong_type() {
+    let (fixture, _, _) = fixture();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = \"dep.txt\"\n");
+
+    let error = Config::default()
+        .query(&fixture.path("a.txt"))
+        .err()
+        .unwrap();
+    assert!(error.contains("must be an array of strings"), "{error}");
+}
+
+#[test]
+fn query_of_a_missing_file_is_an_error() {
+    let (fixture, _, _) = fixture();
+
+    let error = Config::default()
+        .query(&fixture.path("missing.txt"))
+        .err()
+        .unwrap();
+    assert!(error.contains("Invalid file"), "{error}");
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::Fixture;
+
+/// A `.deps.toml` where `a.txt` depends on `dep.txt`, and a `.deps.json` that would say otherwise.
+/// Returns the hash of the commit of `dep.txt`.
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n");
+    fixture.write(".deps.json", "{ \"a.txt\": { \"dependencies\": [] } }\n");
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture.write("dep.txt", "dep");
+    let dependency = fixture.commit("add the dependency");
+    (fixture, dependency)
+}
+
+#[test]
+fn the_toml_file_wins_with_a_warning() {
+    let (fixture, dependency) = fixture();
+
+    let output = fixture.run("", &["a.txt"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Multiple dependencies files found, using"),
+        "{stderr}"
+    );
+    assert!(stderr.contains(".deps.json"), "{stderr}");
+    assert_eq!(common::stdout(output), format!("{dependency}\n"));
+
+    let output = fixture.run("", &["a.txt", "--fail-on-warning"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn selecting_the_format_silences_the_warning() {
+    let (fixture, dependency) = fixture();
+
+    let output = fixture.run("", &["a.txt", "--deps-format", "toml"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("WARN"));
+    assert_eq!(common::stdout(output), format!("{dependency}\n"));
+}
+
+#[test]
+fn an_unsupported_format_is_ignored_or_an_error() {
+    let (fixture, _) = fixture();
+    fixture.git(&["rm", "--quiet", ".deps.toml"]);
+    fixture.write("b.txt", "b");
+    let latest = fixture.commit("remove the toml file");
+
+    // The JSON file can't be read, so the whole directory is monitored
+    let output = fixture.run("", &["a.txt"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("is not supported yet and ignored"),
+        "{stderr}"
+    );
+    assert_eq!(common::stdout(output), format!("{latest}\n"));
+
+    let output = fixture.run("", &["a.txt", "--deps-format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not supported yet"));
+}
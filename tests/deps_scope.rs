@@ -0,0 +1,66 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` depends on `dep.txt`, which was changed after `a.txt`.
+/// Returns the hashes of the commits of `a.txt` and of `dep.txt`.
+fn fixture() -> (Fixture, String, String) {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n");
+    fixture.write("a.txt", "a");
+    fixture.write("dep.txt", "dep");
+    let file = fixture.commit("initial");
+    fixture.write("dep.txt", "changed");
+    let dependency = fixture.commit("change the dependency");
+    (fixture, file, dependency)
+}
+
+#[test]
+fn both_queries_use_the_dependencies_by_default() {
+    let (fixture, _, dependency) = fixture();
+    fixture.write("dep.txt", "uncommitted");
+
+    let output = fixture.stdout("", &["a.txt"]);
+    assert_eq!(output, format!("{dependency} DIRTY\n"));
+    let output = fixture.stdout(
+        "",
+        &["a.txt", "--commit-deps", "all", "--dirty-deps", "all"],
+    );
+    assert_eq!(output, format!("{dependency} DIRTY\n"));
+}
+
+#[test]
+fn the_dirty_check_can_use_only_the_file() {
+    let (fixture, _, dependency) = fixture();
+    fixture.write("dep.txt", "uncommitted");
+
+    let output = fixture.stdout("", &["a.txt", "--dirty-deps", "file"]);
+    assert_eq!(output, format!("{dependency}\n"));
+
+    fixture.write("a.txt", "uncommitted");
+    let output = fixture.stdout("", &["a.txt", "--dirty-deps", "file"]);
+    assert_eq!(output, format!("{dependency} DIRTY\n"));
+}
+
+#[test]
+fn the_commit_query_can_use_only_the_file() {
+    let (fixture, file, _) = fixture();
+    fixture.write("dep.txt", "uncommitted");
+
+    // The dirty check still includes the dependencies
+    let output = fixture.stdout("", &["a.txt", "--commit-deps", "file"]);
+    assert_eq!(output, format!("{file} DIRTY\n"));
+    let output = fixture.stdout(
+        "",
+        &["a.txt", "--commit-deps", "file", "--dirty-deps", "file"],
+    );
+    assert_eq!(output, format!("{file}\n"));
+}
+
+#[test]
+fn an_invalid_scope_is_an_error() {
+    let (fixture, _, _) = fixture();
+
+    let output = fixture.run("", &["a.txt", "--dirty-deps", "none"]);
+    assert_eq!(output.status.code(), Some(1));
+}
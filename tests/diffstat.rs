@@ -0,0 +1,39 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` depends on `dep.txt`, the latest commit also changes an unmonitored file.
+/// Returns the hash of the latest commit.
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n");
+    fixture.write("a.txt", "1\n2\n3\n");
+    fixture.write("dep.txt", "1\n");
+    fixture.commit("initial");
+    fixture.write("a.txt", "1\n2\nthree\nfour\n");
+    fixture.write("dep.txt", "1\n2\n");
+    fixture.write("other.txt", "1\n2\n3\n4\n5\n");
+    let latest = fixture.commit("change everything");
+    (fixture, latest)
+}
+
+#[test]
+fn the_monitored_changes_of_the_latest_commit_are_counted() {
+    let (fixture, latest) = fixture();
+
+    let output = fixture.stdout("", &["a.txt", "--with-diffstat"]);
+    assert_eq!(output, format!("{latest} 2 files +3 -1\n"));
+    let output = fixture.stdout("", &["a.txt", "--with-diffstat", "--format", "json"]);
+    assert!(
+        output.contains("\"files_changed\": 2, \"insertions\": 3, \"deletions\": 1"),
+        "{output}"
+    );
+}
+
+#[test]
+fn a_single_file_is_singular() {
+    let (fixture, latest) = fixture();
+
+    let output = fixture.stdout("", &["a.txt", "--with-diffstat", "--commit-deps", "file"]);
+    assert_eq!(output, format!("{latest} 1 file +2 -1\n"));
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::Fixture;
+
+/// Whether the run of the tool calls `git status`
+fn runs_status(fixture: &Fixture, args: &[&str]) -> bool {
+    let (output, commands) = fixture.run_logging_git("", args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    commands.iter().any(|command| command.contains(" status "))
+}
+
+#[test]
+fn status_only_runs_if_the_output_depends_on_it() {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture.write("a.txt", "uncommitted");
+    fixture.write("no-dirty.txt", "{commit}");
+    fixture.write("dirty.txt", "{commit} {dirty}");
+
+    assert!(runs_status(&fixture, &["a.txt"]));
+    assert!(runs_status(&fixture, &["a.txt", "--format", "json"]));
+    assert!(runs_status(&fixture, &["a.txt", "--template", "dirty.txt"]));
+
+    assert!(!runs_status(&fixture, &["a.txt", "--date"]));
+    assert!(!runs_status(&fixture, &["a.txt", "--age-seconds"]));
+    assert!(!runs_status(&fixture, &["a.txt", "--no-dirty-check"]));
+    assert!(!runs_status(
+        &fixture,
+        &["a.txt", "--format", "json", "--no-dirty-check"]
+    ));
+    assert!(!runs_status(
+        &fixture,
+        &["a.txt", "--template", "no-dirty.txt"]
+    ));
+}
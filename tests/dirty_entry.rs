@@ -0,0 +1,75 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` with `dirty = false` and `b.txt` both depend on `dep.txt`, which has an uncommitted change
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\ndirty = false\n\n\
+         [\"b.txt\"]\ndependencies = [\"dep.txt\"]\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    fixture.write("dep.txt", "dep");
+    let hash = fixture.commit("initial");
+    fixture.write("dep.txt", "uncommitted");
+    (fixture, hash)
+}
+
+#[test]
+fn an_entry_without_dirty_check_is_never_dirty() {
+    let (fixture, hash) = fixture();
+
+    assert_eq!(fixture.stdout("", &["a.txt"]), format!("{hash}\n"));
+    assert_eq!(fixture.stdout("", &["b.txt"]), format!("{hash} DIRTY\n"));
+    // Also together with a file that is checked
+    assert_eq!(
+        fixture.stdout("", &["a.txt", "b.txt"]),
+        format!("a.txt\t{hash}\nb.txt\t{hash} DIRTY\n")
+    );
+}
+
+#[test]
+fn the_other_formats_leave_out_the_dirty_state() {
+    let (fixture, hash) = fixture();
+
+    let output = fixture.stdout("", &["a.txt", "b.txt", "--format", "json"]);
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(!lines[0].contains("dirty"), "{output}");
+    assert!(lines[1].contains("\"dirty\": true"), "{output}");
+
+    fixture.write("template.txt", "{file} {commit} [{dirty}]");
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "b.txt",
+            "--template",
+            "template.txt",
+            "--clean-marker",
+            "clean",
+        ],
+    );
+    assert_eq!(output, format!("a.txt {hash} []\nb.txt {hash} [DIRTY]\n"));
+}
+
+#[test]
+fn dirty_only_skips_the_entry() {
+    let (fixture, _) = fixture();
+
+    assert_eq!(fixture.stdout("", &["a.txt", "--dirty-only"]), "");
+    let output = fixture.stdout("", &["b.txt", "--dirty-only"]);
+    assert!(output.starts_with("DIRTY\n"), "{output}");
+}
+
+#[test]
+fn no_option_enables_the_check() {
+    let (fixture, hash) = fixture();
+
+    let output = fixture.stdout("", &["a.txt", "--dirty-deps", "all", "--fail-on-warning"]);
+    assert_eq!(output, format!("{hash}\n"));
+    let output = fixture.stdout("", &["a.txt", "b.txt", "--no-dirty-check"]);
+    assert_eq!(output, format!("a.txt\t{hash}\nb.txt\t{hash}\n"));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::Fixture;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n");
+    fixture.write("a.txt", "a");
+    fixture.write("dep.txt", "dep");
+    fixture.write("other.txt", "other");
+    fixture.commit("initial");
+    fixture
+}
+
+#[test]
+fn a_clean_file_prints_nothing() {
+    let fixture = fixture();
+    fixture.write("other.txt", "not monitored");
+
+    let (output, commands) =
+        fixture.run_logging_git("", &["a.txt", "--dirty-only", "--fail-on-dirty"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    // No commit is looked up
+    assert!(
+        !commands.iter().any(|command| command.contains(" log ")),
+        "{commands:?}"
+    );
+}
+
+#[test]
+fn a_dirty_file_reports_the_changed_files() {
+    let fixture = fixture();
+    fixture.write("dep.txt", "changed");
+
+    let output = fixture.run("", &["a.txt", "--dirty-only"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "DIRTY\ndep.txt\n");
+
+    let output = fixture.run("", &["a.txt", "--dirty-only", "--fail-on-dirty"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "DIRTY\ndep.txt\n");
+}
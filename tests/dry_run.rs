@@ -0,0 +1,54 @@
+mod common;
+
+use std::fs;
+
+use common::Fixture;
+
+/// The names of all files in the root, besides `.git`
+fn files(fixture: &Fixture) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(&fixture.root)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name != ".git")
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn dry_run_writes_no_files() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    let hash = fixture.commit("initial");
+    fixture.write("existing.txt", "old");
+    let before = files(&fixture);
+
+    let output = fixture.run(
+        "",
+        &[
+            "a.txt",
+            "--dry-run",
+            "--output",
+            "out.txt",
+            "--json-out",
+            "out.json",
+            "--env-out",
+            "out.env",
+            "--touch",
+            "existing.txt",
+        ],
+    );
+    let stdout = common::stdout(output.clone());
+    // The output still goes to stdout
+    assert_eq!(stdout.trim_end(), hash);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("would write"), "{stderr}");
+    assert!(stderr.contains("would touch existing.txt"), "{stderr}");
+
+    assert_eq!(files(&fixture), before);
+    assert_eq!(
+        fs::read_to_string(fixture.path("existing.txt")).unwrap(),
+        "old"
+    );
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn every_entry_is_dumped_with_its_files() {
+    let fixture = Fixture::new();
+    fixture.write(
+        "docs/.deps.toml",
+        "[\"file1.typ\"]\ndependencies = [\"dep1.typ\", \":!dep2.typ\"]\naliases = [\"old/file1.typ\"]\n\n\
+         [\"file2.typ\"]\n",
+    );
+    fixture.write("docs/file1.typ", "1");
+    fixture.write("docs/file3.typ", "3");
+    let docs = fixture.path("docs");
+    let docs = docs.to_str().unwrap();
+
+    // Without a commit, as git is not queried
+    let output = fixture.stdout("", &["docs/file3.typ", "--dump-resolved"]);
+    assert_eq!(
+        output.trim_end(),
+        format!(
+            "{{ \"file1.typ\": {{ \"files\": [\"file1.typ\", \"dep1.typ\", \":(exclude)dep2.typ\"], \
+             \"aliases\": [\"old/file1.typ\"], \"from_fallback\": false }}, \
+             \"file2.typ\": {{ \"files\": [\"{docs}\"], \"aliases\": [], \"from_fallback\": true }}, \
+             \"file3.typ\": {{ \"files\": [\"{docs}\"], \"aliases\": [], \"from_fallback\": true }} }}"
+        )
+    );
+}
+
+#[test]
+fn the_file_is_dumped_without_a_dependencies_file() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    let root = fixture.root.to_str().unwrap().to_string();
+
+    let output = fixture.stdout("", &["a.txt", "--dump-resolved"]);
+    assert_eq!(
+        output.trim_end(),
+        format!("{{ \"a.txt\": {{ \"files\": [\"{root}\"], \"aliases\": [], \"from_fallback\": true }} }}")
+    );
+    // The fallback is not reported as a warning here
+    assert!(
+        !String::from_utf8_lossy(&fixture.run("", &["a.txt", "--dump-resolved"]).stderr)
+            .contains("WARN")
+    );
+}
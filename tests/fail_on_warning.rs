@@ -0,0 +1,53 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` and `docs/b.txt` without `.deps.toml` entries, which is a warning for each
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.write("docs/b.txt", "b");
+    fixture.commit("initial");
+    fixture
+}
+
+#[test]
+fn warnings_are_only_logged_by_default() {
+    let fixture = fixture();
+
+    let output = fixture.run("", &["a.txt", "docs/b.txt"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 2);
+}
+
+#[test]
+fn all_warnings_fail_the_run() {
+    let fixture = fixture();
+
+    for strict in [&["--fail-on-warning"][..], &["-W", "error"]] {
+        let mut args = vec!["a.txt", "docs/b.txt"];
+        args.extend(strict);
+        let output = fixture.run("", &args);
+        assert_eq!(output.status.code(), Some(1), "{strict:?}");
+        assert!(output.stdout.is_empty());
+        // Both warnings are reported, not only the first
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        for file in ["a.txt", "b.txt"] {
+            assert!(
+                stderr.contains(&format!("No dependencies entry found for file \"{file}\"")),
+                "{stderr}"
+            );
+        }
+    }
+}
+
+#[test]
+fn without_warnings_the_run_succeeds() {
+    let fixture = fixture();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.commit("entry");
+    let hash = fixture.git(&["log", "-1", "--format=%H", "--", "a.txt"]);
+
+    let output = fixture.stdout("", &["a.txt", "--fail-on-warning"]);
+    assert_eq!(output, format!("{hash}\n"));
+}
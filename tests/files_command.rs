@@ -0,0 +1,51 @@
+mod common;
+
+use common::Fixture;
+
+/// `docs/a.txt`, with `docs/dep.txt` and `docs/sub/b.txt` changed after it, one commit each.
+/// Returns the hashes of the commits in that order.
+fn fixture() -> (Fixture, Vec<String>) {
+    let fixture = Fixture::new();
+    let mut hashes = Vec::new();
+    for path in ["docs/a.txt", "docs/dep.txt", "docs/sub/b.txt"] {
+        fixture.write(path, path);
+        hashes.push(fixture.commit(path));
+    }
+    (fixture, hashes)
+}
+
+#[test]
+fn the_listed_files_are_monitored() {
+    let (fixture, hashes) = fixture();
+
+    // Relative to the base directory, the directory of the file
+    let output = fixture.stdout("", &["docs/a.txt", "--files-command", "echo dep.txt"]);
+    assert_eq!(output, format!("{}\n", hashes[1]));
+    let output = fixture.stdout(
+        "",
+        &[
+            "docs/a.txt",
+            "--files-command",
+            "printf 'dep.txt\\0sub/b.txt\\0'",
+        ],
+    );
+    assert_eq!(output, format!("{}\n", hashes[2]));
+
+    fixture.write("docs/dep.txt", "uncommitted");
+    let output = fixture.stdout("", &["docs/a.txt", "--files-command", "echo dep.txt"]);
+    assert_eq!(output, format!("{} DIRTY\n", hashes[1]));
+}
+
+#[test]
+fn a_failing_command_aborts() {
+    let (fixture, _) = fixture();
+
+    for command in ["echo dep.txt; exit 3", "true"] {
+        let output = fixture.run("", &["docs/a.txt", "--files-command", command]);
+        assert_eq!(output.status.code(), Some(1), "{command}");
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(command), "{stderr}");
+        assert!(!stderr.contains("panicked"), "{stderr}");
+    }
+}
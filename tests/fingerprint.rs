@@ -0,0 +1,86 @@
+mod common;
+
+use common::Fixture;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n");
+    fixture.write("a.txt", "a");
+    fixture.write("dep.txt", "dep");
+    fixture.commit("initial");
+    fixture
+}
+
+fn fingerprint(fixture: &Fixture, staged: bool) -> String {
+    let mut args = vec!["a.txt", "--fingerprint"];
+    if staged {
+        args.push("--staged");
+    }
+    let output = fixture.stdout("", &args);
+    assert!(!output.contains("DIRTY"), "{output}");
+    output
+}
+
+#[test]
+fn the_fingerprint_follows_the_working_tree() {
+    let fixture = fixture();
+    let committed = fingerprint(&fixture, false);
+
+    fixture.write("dep.txt", "changed");
+    let changed = fingerprint(&fixture, false);
+    assert_ne!(changed, committed);
+
+    fixture.write("dep.txt", "dep");
+    assert_eq!(fingerprint(&fixture, false), committed);
+}
+
+#[test]
+fn the_staged_fingerprint_reflects_the_staged_content() {
+    let fixture = fixture();
+    let committed = fingerprint(&fixture, true);
+    assert_eq!(committed, fingerprint(&fixture, false));
+
+    fixture.write("dep.txt", "staged");
+    fixture.git(&["add", "dep.txt"]);
+    let staged = fingerprint(&fixture, true);
+    assert_ne!(staged, committed);
+    // Same as the working tree with the staged content
+    assert_eq!(staged, fingerprint(&fixture, false));
+
+    // Unstaged changes don't affect it
+    fixture.write("dep.txt", "unstaged");
+    fixture.write("untracked.txt", "untracked");
+    assert_eq!(fingerprint(&fixture, true), staged);
+    assert_ne!(fingerprint(&fixture, false), staged);
+}
+
+#[test]
+fn paths_with_quotes_are_hashed() {
+    let fixture = Fixture::new();
+    fixture.write("q/we\"ird.txt", "weird");
+    fixture.commit("initial");
+
+    // Without an entry, the directory is monitored, which only holds this file
+    let args = ["q/we\"ird.txt", "--fingerprint"];
+    let committed = fixture.stdout("", &args);
+    assert_eq!(
+        fixture.stdout("", &["q/we\"ird.txt", "--fingerprint", "--staged"]),
+        committed
+    );
+    fixture.write("q/we\"ird.txt", "changed");
+    assert_ne!(fixture.stdout("", &args), committed);
+}
+
+#[test]
+fn no_files_to_hash_is_an_error() {
+    let fixture = fixture();
+    fixture.git(&["rm", "--quiet", "--cached", "a.txt", "dep.txt"]);
+
+    let output = fixture.run("", &["a.txt", "--fingerprint", "--staged"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Failed to compute the fingerprint of a.txt"),
+        "{stderr}"
+    );
+}
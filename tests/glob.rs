@@ -0,0 +1,59 @@
+mod common;
+
+use common::Fixture;
+
+/// `src/a.rs`, `src/nested/b.rs` and `src/c.txt`, each changed in its own commit.
+/// Returns the hashes of the commits in that order.
+fn fixture() -> (Fixture, Vec<String>) {
+    let fixture = Fixture::new();
+    let mut hashes = Vec::new();
+    for path in ["src/nested/b.rs", "src/a.rs", "src/c.txt"] {
+        fixture.write(path, path);
+        hashes.push(fixture.commit(path));
+    }
+    (fixture, hashes)
+}
+
+#[test]
+fn a_glob_is_monitored_as_one_group() {
+    let (fixture, hashes) = fixture();
+
+    let output = fixture.stdout("", &["src/**/*.rs"]);
+    assert_eq!(output, format!("{}\n", hashes[1]));
+    let output = fixture.stdout("", &["src/*.txt"]);
+    assert_eq!(output, format!("{}\n", hashes[2]));
+
+    // Only matching files make it dirty
+    fixture.write("src/c.txt", "uncommitted");
+    let output = fixture.stdout("", &["src/**/*.rs"]);
+    assert_eq!(output, format!("{}\n", hashes[1]));
+    fixture.write("src/nested/b.rs", "uncommitted");
+    let output = fixture.stdout("", &["src/**/*.rs"]);
+    assert_eq!(output, format!("{} DIRTY\n", hashes[1]));
+}
+
+#[test]
+fn an_existing_file_is_not_a_glob() {
+    let (fixture, _) = fixture();
+    fixture.write("src/[x].rs", "literal");
+    let hash = fixture.commit("literal");
+    fixture.write("src/x.rs", "matches the pattern");
+
+    let output = fixture.stdout("", &["src/[x].rs", "--no-dirty-check"]);
+    assert_eq!(output, format!("{hash}\n"));
+}
+
+#[test]
+fn a_glob_without_matches_is_an_error() {
+    let (fixture, _) = fixture();
+
+    let output = fixture.run("", &["src/*.md"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No tracked files match src/*.md"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` is changed by Alice on a branch, which is rebased onto a later commit of `main`.
+/// Returns the hash of the rebased commit.
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture.git(&["checkout", "--quiet", "-b", "side"]);
+    fixture.write("a.txt", "changed");
+    fixture.git(&["add", "a.txt"]);
+    fixture.git(&[
+        "commit",
+        "--quiet",
+        "--message",
+        "change a",
+        "--author",
+        "Alice <alice@example.com>",
+        "--date",
+        "2024-01-02T12:00:00+00:00",
+    ]);
+    fixture.git(&["checkout", "--quiet", "main"]);
+    fixture.write("other.txt", "unrelated");
+    fixture.commit("unrelated");
+    fixture.git(&["checkout", "--quiet", "side"]);
+    fixture.git(&["rebase", "--quiet", "main"]);
+    let rebased = fixture.git(&["rev-parse", "HEAD"]);
+    (fixture, rebased)
+}
+
+#[test]
+fn the_author_survives_the_rebase() {
+    let (fixture, rebased) = fixture();
+    let committed = fixture.git(&["log", "-1", "--format=%cs"]);
+    assert_ne!(committed, "2024-01-02");
+
+    let output = fixture.stdout("", &["a.txt", "--date", "--identity", "author"]);
+    assert_eq!(output, "2024-01-02\n");
+    let output = fixture.stdout("", &["a.txt", "--date"]);
+    assert_eq!(output, format!("{committed}\n"));
+    let output = fixture.stdout("", &["a.txt", "--date", "--identity", "committer"]);
+    assert_eq!(output, format!("{committed}\n"));
+
+    let output = fixture.stdout("", &["a.txt", "--format", "json", "--identity", "author"]);
+    assert!(
+        output.contains(&format!("\"commit\": \"{rebased}\"")),
+        "{output}"
+    );
+    assert!(output.contains("\"date\": \"2024-01-02\""), "{output}");
+    assert!(
+        output.contains("\"name\": \"Alice\", \"email\": \"alice@example.com\""),
+        "{output}"
+    );
+    let output = fixture.stdout(
+        "",
+        &["a.txt", "--format", "json", "--identity", "committer"],
+    );
+    assert!(
+        output.contains("\"name\": \"Test\", \"email\": \"test@example.com\""),
+        "{output}"
+    );
+    // Without --identity, the person is not reported
+    let output = fixture.stdout("", &["a.txt", "--format", "json"]);
+    assert!(!output.contains("\"name\""), "{output}");
+}
+
+#[test]
+fn the_author_is_preferred_if_present() {
+    let (fixture, _) = fixture();
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "--format",
+            "json",
+            "--identity",
+            "author-or-committer",
+        ],
+    );
+    assert!(
+        output.contains("\"date\": \"2024-01-02\", \"dirty\": false, \"name\": \"Alice\""),
+        "{output}"
+    );
+}
+
+#[test]
+fn the_committer_is_used_without_an_author_name() {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "a");
+    fixture.git(&["add", "--all"]);
+    // git refuses to create a commit without a name, so the object is written directly
+    let tree = fixture.git(&["write-tree"]);
+    let commit = format!(
+        "tree {tree}\nauthor <import@example.com> 1704196800 +0000\n\
+         committer Importer <importer@example.com> 1706875200 +0000\n\nimported\n"
+    );
+    fixture.write(".git/imported-commit", commit);
+    let hash = fixture.git(&[
+        "hash-object",
+        "-t",
+        "commit",
+        "-w",
+        "--literally",
+        ".git/imported-commit",
+    ]);
+    fixture.git(&["update-ref", "refs/heads/main", &hash]);
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "--format",
+            "json",
+            "--identity",
+            "author-or-committer",
+        ],
+    );
+    assert!(
+        output.contains("\"date\": \"2024-02-02\", \"dirty\": false, \"name\": \"Importer\""),
+        "{output}"
+    );
+    let output = fixture.stdout("", &["a.txt", "--date", "--identity", "author"]);
+    assert_eq!(output, "2024-01-02\n");
+}
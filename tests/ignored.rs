@@ -0,0 +1,93 @@
+mod common;
+
+use common::Fixture;
+
+/// A repository where `a.txt` and `sub/b.txt` depend on the ignored `gen/out.txt`
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write(".gitignore", "gen/\n");
+    fixture.write("a.txt", "a");
+    fixture.write("sub/b.txt", "b");
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = [\"gen/out.txt\"]\n",
+    );
+    fixture.write(
+        "sub/.deps.toml",
+        "[\"b.txt\"]\ndependencies = [\"../gen/out.txt\"]\n",
+    );
+    fixture.commit("initial");
+    fixture.write("gen/out.txt", "generated");
+    fixture
+}
+
+#[test]
+fn an_ignored_dependency_is_reported_once() {
+    let fixture = fixture();
+
+    let (output, commands) = fixture.run_logging_git("", &["a.txt", "sub/b.txt"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.matches("\"gen/out.txt\" is ignored by git").count(),
+        1,
+        "{stderr}"
+    );
+    assert!(!stderr.contains("a.txt\" is ignored"), "{stderr}");
+    // One check for both files in the repository
+    let checks = commands
+        .iter()
+        .filter(|command| command.contains("check-ignore"))
+        .count();
+    assert_eq!(checks, 1, "{commands:?}");
+}
+
+#[test]
+fn an_ignored_dependency_fails_with_fail_on_warning() {
+    let fixture = fixture();
+
+    let output = fixture.run("", &["a.txt", "--fail-on-warning"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn include_ignored_skips_the_check() {
+    let fixture = fixture();
+
+    let (output, commands) =
+        fixture.run_logging_git("", &["a.txt", "--fingerprint", "--include-ignored"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("is ignored by git"), "{stderr}");
+    assert!(
+        !commands
+            .iter()
+            .any(|command| command.contains("check-ignore")),
+        "{commands:?}"
+    );
+}
+
+#[test]
+fn many_ignored_dependencies_are_checked() {
+    let fixture = fixture();
+    // More paths than fit into the pipes, in both directions
+    let dependencies: Vec<String> = (0..20000)
+        .map(|i| format!("\"gen/file-{i:05}.txt\""))
+        .collect();
+    fixture.write(
+        ".deps.toml",
+        format!(
+            "[\"a.txt\"]\ndependencies = [{}]\n",
+            dependencies.join(", ")
+        ),
+    );
+
+    let output = fixture.run("", &["a.txt"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("\"gen/file-19999.txt\" is ignored by git"),
+        "{}",
+        &stderr[stderr.len().saturating_sub(1000)..]
+    );
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::Fixture;
+
+const INDEX: &str = ".git/index.toml";
+
+/// A history with a deleted dependency, a renamed file and a deleted file in a directory
+fn history() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.write("dep.txt", "dep");
+    fixture.write("old.txt", "renamed");
+    fixture.write("docs/x.txt", "x");
+    fixture.write("docs/y.txt", "y");
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n\n[\"new.txt\"]\ndependencies = []\naliases = [\"old.txt\"]\n",
+    );
+    fixture.commit("initial");
+    fixture.git(&["mv", "old.txt", "new.txt"]);
+    fixture.commit("rename");
+    fixture.write("new.txt", "changed");
+    fixture.commit("change the renamed file");
+    fixture.git(&["rm", "--quiet", "dep.txt"]);
+    fixture.commit("delete the dependency");
+    fixture.git(&["rm", "--quiet", "docs/y.txt"]);
+    fixture.commit("delete a file in the directory");
+    fixture.write("a.txt", "changed");
+    fixture.commit("change a");
+    fixture
+}
+
+/// The index answers like git, for every kind of path
+#[test]
+fn use_index_matches_git() {
+    let fixture = history();
+    fixture.stdout("", &["--build-index", INDEX]);
+
+    // The deletion in the directory is newer than the change of its remaining file
+    for files in [
+        vec!["a.txt"],
+        vec!["new.txt"],
+        vec!["docs"],
+        vec!["a.txt", "new.txt", "docs"],
+    ] {
+        let mut args = files.clone();
+        args.push("--no-dirty-check");
+        let expected = fixture.stdout("", &args);
+        args.extend(["--use-index", INDEX]);
+        assert_eq!(fixture.stdout("", &args), expected, "{files:?}");
+    }
+
+    // Before the change of a.txt, the deleted dependency is the latest change
+    fixture.git(&["checkout", "--quiet", "HEAD~1"]);
+    fixture.stdout("", &["--build-index", INDEX]);
+    let expected = fixture.git(&["log", "-1", "--format=%H", "--", "a.txt", "dep.txt"]);
+    let output = fixture.stdout("", &["a.txt", "--use-index", INDEX]);
+    assert_eq!(output.trim_end(), expected);
+    assert_ne!(
+        expected,
+        fixture.git(&["log", "-1", "--format=%H", "--", "a.txt"])
+    );
+}
+
+#[test]
+fn lookups_do_not_run_git_log() {
+    let fixture = history();
+    let (output, commands) = fixture.run_logging_git("", &["--build-index", INDEX]);
+    common::stdout(output);
+    // One walk of the whole repository, without the files on the command line
+    let walks: Vec<&String> = commands
+        .iter()
+        .filter(|command| command.contains(" log "))
+        .collect();
+    assert_eq!(walks.len(), 1, "{commands:?}");
+    assert!(walks[0].ends_with(" -- ."), "{}", walks[0]);
+
+    let (output, commands) =
+        fixture.run_logging_git("", &["a.txt", "new.txt", "docs", "--use-index", INDEX]);
+    common::stdout(output);
+    assert!(
+        !commands.iter().any(|command| command.contains(" log ")),
+        "{commands:?}"
+    );
+}
+
+#[test]
+fn a_stale_index_is_ignored() {
+    let fixture = history();
+    fixture.stdout("", &["--build-index", INDEX]);
+    fixture.write("a.txt", "changed again");
+    let latest = fixture.commit("after the index");
+
+    let output = fixture.run("", &["a.txt", "--use-index", INDEX]);
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(stderr.contains("is stale"), "{stderr}");
+    assert_eq!(common::stdout(output).trim_end(), latest);
+
+    let output = fixture.run("", &["a.txt", "--use-index", INDEX, "--fail-on-warning"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn uncommitted_changes_are_still_marked() {
+    let fixture = history();
+    fixture.stdout("", &["--build-index", INDEX]);
+    let latest = fixture.git(&["rev-parse", "HEAD"]);
+    fixture.write("a.txt", "uncommitted");
+
+    let (output, commands) = fixture.run_logging_git("", &["a.txt", "--use-index", INDEX]);
+    assert_eq!(common::stdout(output), format!("{latest} DIRTY\n"));
+    assert!(
+        !commands.iter().any(|command| command.contains(" log ")),
+        "{commands:?}"
+    );
+    // Only the modified file is marked
+    let renamed = fixture.git(&["log", "-1", "--format=%H", "--", "new.txt"]);
+    let output = fixture.stdout("", &["a.txt", "new.txt", "--use-index", INDEX]);
+    assert_eq!(
+        output,
+        format!("a.txt\t{latest} DIRTY\nnew.txt\t{renamed}\n")
+    );
+
+    let output = fixture.stdout("", &["a.txt", "--use-index", INDEX, "--format", "json"]);
+    assert!(output.contains("\"dirty\": true"), "{output}");
+    let output = fixture.stdout("", &["a.txt", "--use-index", INDEX, "--no-dirty-check"]);
+    assert_eq!(output, format!("{latest}\n"));
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::Fixture;
+
+/// A user config that changes the output of the git commands the tool parses
+const CONFLICTING_CONFIG: &str = "[core]\n\tabbrev = 12\n[status]\n\tshowUntrackedFiles = no\n";
+
+#[test]
+fn user_config_does_not_change_the_output() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = [\"new.txt\"]\n");
+    let commit = fixture.commit("initial");
+    fixture.write("new.txt", "untracked");
+    fixture.write(".git/user.gitconfig", CONFLICTING_CONFIG);
+    let config = fixture.path(".git/user.gitconfig");
+
+    let run = |args: &[&str]| {
+        common::stdout(fixture.run_with("", args, |command| {
+            command.env("GIT_CONFIG_GLOBAL", &config);
+        }))
+    };
+    let output = run(&["a.txt", "--format", "json"]);
+    assert!(
+        output.contains(&format!("\"short\": \"{}\"", &commit[..7])),
+        "{output}"
+    );
+    assert!(output.contains("\"dirty\": true"), "{output}");
+
+    let output = run(&["a.txt", "--format", "json", "--no-isolation"]);
+    assert!(
+        output.contains(&format!("\"short\": \"{}\"", &commit[..12])),
+        "{output}"
+    );
+    assert!(output.contains("\"dirty\": false"), "{output}");
+}
+
+#[test]
+fn every_git_command_is_isolated() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    let (output, commands) = fixture.run_logging_git("", &["a.txt"]);
+    common::stdout(output);
+    assert!(
+        commands
+            .iter()
+            .any(|command| command.contains("rev-parse --is-inside-work-tree")),
+        "{commands:?}"
+    );
+    for command in &commands {
+        assert!(command.contains("-c core.quotePath=false"), "{command}");
+    }
+
+    let (output, commands) = fixture.run_logging_git("", &["a.txt", "--no-isolation"]);
+    common::stdout(output);
+    for command in &commands {
+        assert!(!command.contains("-c "), "{command}");
+    }
+}
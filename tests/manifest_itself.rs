@@ -0,0 +1,44 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn the_manifest_is_monitored_alone() {
+    let fixture = Fixture::new();
+    fixture.write("docs/.deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("docs/a.txt", "a");
+    let manifest = fixture.commit("initial");
+    fixture.write("docs/a.txt", "changed");
+    fixture.commit("change another file of the directory");
+
+    let output = fixture.run("", &["docs/.deps.toml"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("WARN"));
+    assert_eq!(common::stdout(output), format!("{manifest}\n"));
+
+    fixture.write(
+        "docs/.deps.toml",
+        "[\"a.txt\"]\ndependencies = [] # edited\n",
+    );
+    let output = fixture.stdout("", &["docs/.deps.toml"]);
+    assert_eq!(output, format!("{manifest} DIRTY\n"));
+}
+
+#[test]
+fn the_manifest_can_have_an_entry() {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\".deps.toml\"]\ndependencies = [\"config.txt\"]\n\n[\"a.txt\"]\ndependencies = [\".deps.toml\"]\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("config.txt", "config");
+    fixture.commit("initial");
+    fixture.write("config.txt", "changed");
+    let config = fixture.commit("change the config");
+
+    let output = fixture.stdout("", &[".deps.toml"]);
+    assert_eq!(output, format!("{config}\n"));
+    // Dependencies are not followed transitively, so the manifest is just a file for a.txt
+    let output = fixture.stdout("", &["a.txt", "--print-pathspecs"]);
+    assert_eq!(output, "'a.txt' '.deps.toml'\n");
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::Fixture;
+
+/// `docs/a.txt` without a `.deps.toml` entry, with files one and two levels below it.
+/// Returns the hashes of the initial commit and of a change two levels below.
+fn fixture() -> (Fixture, String, String) {
+    let fixture = Fixture::new();
+    fixture.write("docs/a.txt", "a");
+    fixture.write("docs/sub/b.txt", "b");
+    fixture.write("docs/sub/deep/c.txt", "c");
+    let initial = fixture.commit("initial");
+    fixture.write("docs/sub/deep/c.txt", "changed");
+    let deep = fixture.commit("change a deep file");
+    (fixture, initial, deep)
+}
+
+#[test]
+fn files_beyond_the_depth_are_excluded() {
+    let (fixture, initial, deep) = fixture();
+
+    let output = fixture.stdout("", &["docs/a.txt"]);
+    assert_eq!(output, format!("{deep}\n"));
+    let output = fixture.stdout("", &["docs/a.txt", "--max-depth", "2"]);
+    assert_eq!(output, format!("{initial}\n"));
+    let output = fixture.stdout("", &["docs/a.txt", "--max-depth", "3"]);
+    assert_eq!(output, format!("{deep}\n"));
+
+    // Also for the dirty check, including untracked files
+    fixture.write("docs/sub/deep/d.txt", "untracked");
+    let output = fixture.stdout("", &["docs/a.txt", "--max-depth", "2"]);
+    assert_eq!(output, format!("{initial}\n"));
+    fixture.write("docs/sub/e.txt", "untracked");
+    let output = fixture.stdout("", &["docs/a.txt", "--max-depth", "2"]);
+    assert_eq!(output, format!("{initial} DIRTY\n"));
+}
+
+#[test]
+fn entries_of_the_dependencies_file_are_not_limited() {
+    let (fixture, _, deep) = fixture();
+    fixture.write("docs/.deps.toml", "[\"a.txt\"]\ndependencies = [\"sub\"]\n");
+
+    let output = fixture.stdout("", &["docs/a.txt", "--max-depth", "1"]);
+    assert_eq!(output, format!("{deep}\n"));
+}
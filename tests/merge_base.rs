@@ -0,0 +1,55 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn merge_base_of_divergent_branches() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    let base = fixture.commit("base");
+    fixture.write("a.txt", "main");
+    fixture.commit("on main");
+    fixture.git(&["checkout", "--quiet", "-b", "feature", &base]);
+    fixture.write("b.txt", "unrelated");
+    fixture.commit("unrelated on feature");
+    fixture.write("a.txt", "feature");
+    fixture.commit("on feature");
+
+    let output = fixture.stdout("", &["a.txt", "--merge-base", "main"]);
+    assert_eq!(output.trim_end(), base);
+    let output = fixture.stdout("", &["a.txt", "--merge-base", "main", "--hash-width", "10"]);
+    assert_eq!(output.trim_end(), &base[..10]);
+
+    // From the other side, the latest commit is the one on main
+    fixture.git(&["checkout", "--quiet", "main"]);
+    let output = fixture.stdout("", &["a.txt", "--merge-base", "feature"]);
+    assert_eq!(output.trim_end(), base);
+}
+
+#[test]
+fn an_unknown_ref_is_an_error() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    let output = fixture.run("", &["a.txt", "--merge-base", "no-such-branch"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no-such-branch"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn no_common_ancestor_is_an_error() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture.git(&["checkout", "--quiet", "--orphan", "orphan"]);
+    fixture.commit("orphan");
+
+    fixture.git(&["checkout", "--quiet", "main"]);
+    let output = fixture.run("", &["a.txt", "--merge-base", "orphan"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No common ancestor"), "{stderr}");
+}
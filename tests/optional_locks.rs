@@ -0,0 +1,43 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn every_git_command_gets_no_optional_locks() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    let (output, commands) = fixture.run_logging_git("", &["a.txt", "--no-optional-locks"]);
+    common::stdout(output);
+    assert!(commands.len() > 2, "{commands:?}");
+    for command in &commands {
+        assert!(command.starts_with("--no-optional-locks "), "{command}");
+    }
+
+    let (output, commands) = fixture.run_logging_git("", &["a.txt"]);
+    common::stdout(output);
+    for command in &commands {
+        assert!(!command.contains("--no-optional-locks"), "{command}");
+    }
+}
+
+#[test]
+fn enabled_in_ci_unless_optional_locks() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    let in_ci = |args: &[&str], ci: &str| {
+        let (output, commands) = fixture.run_logging_git_with("", args, |command| {
+            command.env("CI", ci);
+        });
+        common::stdout(output);
+        commands
+            .iter()
+            .all(|command| command.starts_with("--no-optional-locks "))
+    };
+    assert!(in_ci(&["a.txt"], "true"));
+    assert!(!in_ci(&["a.txt"], "false"));
+    assert!(!in_ci(&["a.txt", "--optional-locks"], "true"));
+}
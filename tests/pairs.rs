@@ -0,0 +1,72 @@
+mod common;
+
+use common::Fixture;
+
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"it's here.md\"]\ndependencies = []\n\n[\"x=y.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("it's here.md", "b");
+    fixture.write("x=y.txt", "c");
+    let head = fixture.commit("initial");
+    (fixture, head)
+}
+
+#[test]
+fn every_file_is_a_quoted_pair() {
+    let (fixture, head) = fixture();
+
+    let output = fixture.stdout("", &["--pairs", "a.txt", "it's here.md", "x=y.txt"]);
+    assert_eq!(
+        output,
+        format!("'a.txt'='{head}'\n'it'\\''s here.md'='{head}'\n'x=y.txt'='{head}'\n")
+    );
+    // Not marked, the date is printed alone
+    fixture.write("a.txt", "uncommitted");
+    let output = fixture.stdout(
+        "",
+        &["--format", "pairs", "--date", "a.txt", "it's here.md"],
+    );
+    let date = fixture.git(&["log", "-1", "--format=%cs"]);
+    assert_eq!(
+        output,
+        format!("'a.txt'='{date}'\n'it'\\''s here.md'='{date}'\n")
+    );
+}
+
+#[test]
+fn a_single_file_is_keyed_too() {
+    let (fixture, head) = fixture();
+
+    let output = fixture.stdout("", &["--pairs", "x=y.txt"]);
+    assert_eq!(output, format!("'x=y.txt'='{head}'\n"));
+}
+
+#[test]
+fn a_dirty_hash_is_marked() {
+    let (fixture, head) = fixture();
+    fixture.write("it's here.md", "uncommitted");
+
+    let output = fixture.stdout("", &["--pairs", "a.txt", "it's here.md"]);
+    assert_eq!(
+        output,
+        format!("'a.txt'='{head}'\n'it'\\''s here.md'='{head} DIRTY'\n")
+    );
+    let output = fixture.stdout(
+        "",
+        &[
+            "--pairs",
+            "a.txt",
+            "it's here.md",
+            "--clean-marker",
+            "clean",
+        ],
+    );
+    assert_eq!(
+        output,
+        format!("'a.txt'='{head} clean'\n'it'\\''s here.md'='{head} DIRTY'\n")
+    );
+}
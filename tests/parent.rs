@@ -0,0 +1,49 @@
+mod common;
+
+use common::Fixture;
+
+/// `b.txt` is only changed in the root commit. Returns the hash of the latest change of `a.txt`.
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    fixture.commit("initial");
+    fixture.write("other.txt", "unrelated");
+    fixture.commit("unrelated");
+    fixture.write("a.txt", "changed");
+    let change = fixture.commit("change a");
+    (fixture, change)
+}
+
+#[test]
+fn the_first_parent_of_the_latest_commit_is_reported() {
+    let (fixture, change) = fixture();
+    let parent = fixture.git(&["rev-parse", &format!("{change}^")]);
+
+    // Also while dirty, as the marker is not appended
+    fixture.write("a.txt", "uncommitted");
+    let output = fixture.stdout("", &["a.txt", "--parent"]);
+    assert_eq!(output, format!("{parent}\n"));
+    let output = fixture.stdout("", &["a.txt", "--parent", "--hash-width", "8"]);
+    assert_eq!(output, format!("{}\n", &parent[..8]));
+    let output = fixture.stdout("", &["a.txt", "--parent", "--format", "shell"]);
+    assert!(output.contains(&format!("PARENT='{parent}'")), "{output}");
+    assert!(output.contains(&format!("COMMIT='{change}'")), "{output}");
+}
+
+#[test]
+fn a_root_commit_has_no_parent() {
+    let (fixture, _) = fixture();
+
+    let output = fixture.run("", &["b.txt", "--parent"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("ERROR"));
+
+    let output = fixture.stdout("", &["b.txt", "--parent", "--empty-value", "none"]);
+    assert_eq!(output, "none\n");
+}
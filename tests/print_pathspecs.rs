@@ -0,0 +1,77 @@
+mod common;
+
+use std::process::Command;
+
+use common::Fixture;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write(
+        "docs/.deps.toml",
+        "[\"a.txt\"]\ndependencies = [\"*.png\", \":!sub\", \":^old\", \":/top.txt\", \"with space.txt\"]\n\n\
+         [\"b.txt\"]\ndependencies = [\"a.txt\"]\n",
+    );
+    fixture.write("docs/a.txt", "a");
+    fixture.write("docs/b.txt", "b");
+    fixture.write("docs/image.png", "png");
+    fixture.write("docs/sub/image.png", "png");
+    fixture.write("top.txt", "top");
+    fixture.commit("initial");
+    fixture
+}
+
+#[test]
+fn excludes_are_rendered_in_the_long_form() {
+    let fixture = fixture();
+
+    let output = fixture.stdout("docs", &["a.txt", "--print-pathspecs"]);
+    assert_eq!(
+        output,
+        "'a.txt' '*.png' ':(exclude)sub' ':(exclude)old' ':(top)top.txt' 'with space.txt'\n"
+    );
+}
+
+#[test]
+fn several_files_are_prefixed() {
+    let fixture = fixture();
+
+    let output = fixture.stdout("docs", &["a.txt", "b.txt", "--print-pathspecs"]);
+    assert_eq!(
+        output,
+        "a.txt\t'a.txt' '*.png' ':(exclude)sub' ':(exclude)old' ':(top)top.txt' 'with space.txt'\n\
+         b.txt\t'b.txt' 'a.txt'\n"
+    );
+}
+
+#[test]
+fn the_pathspecs_reproduce_the_query() {
+    let fixture = fixture();
+    fixture.write("docs/sub/image.png", "excluded");
+    fixture.commit("change an excluded file");
+    fixture.write("top.txt", "changed");
+    let top = fixture.commit("change a file at the top");
+    fixture.write("docs/sub/image.png", "excluded again");
+    fixture.commit("change an excluded file again");
+
+    // Like the example in the README
+    let pathspecs = fixture.stdout("docs", &["a.txt", "--print-pathspecs"]);
+    let output = Command::new("sh")
+        .current_dir(fixture.path("docs"))
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .args([
+            "-c",
+            "eval git log -1 --format=%H -- \"$1\"",
+            "sh",
+            pathspecs.trim_end(),
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        top,
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(fixture.stdout("docs", &["a.txt"]), format!("{top}\n"));
+}
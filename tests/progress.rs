@@ -0,0 +1,24 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn no_progress_is_shown_if_stderr_is_not_a_terminal() {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    fixture.commit("initial");
+
+    let plain = fixture.run("", &["a.txt", "b.txt"]);
+    // The output of the test is a pipe
+    let output = fixture.run("", &["a.txt", "b.txt", "--progress"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, plain.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("(100%)"), "{stderr}");
+    assert!(!stderr.contains('\r'), "{stderr}");
+}
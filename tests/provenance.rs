@@ -0,0 +1,25 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn dependencies_outside_the_directory_are_reported_relative_to_the_root() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.write("sub/b.txt", "b");
+    fixture.write(
+        "sub/.deps.toml",
+        "[\"b.txt\"]\ndependencies = [\"../a.txt\"]\n",
+    );
+    let commit = fixture.commit("initial");
+
+    let output = fixture.stdout("sub", &["b.txt", "--provenance"]);
+    let a_blob = fixture.git(&["rev-parse", "HEAD:a.txt"]);
+    assert!(
+        output.contains(&format!(
+            "{{ \"path\": \"a.txt\", \"commit\": \"{commit}\", \"blob\": \"{a_blob}\", \"committed_blob\": \"{a_blob}\", \"dirty\": false }}"
+        )),
+        "{output}"
+    );
+    assert!(output.contains("\"path\": \"sub/b.txt\""), "{output}");
+}
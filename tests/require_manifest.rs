@@ -0,0 +1,62 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn a_missing_manifest_is_an_error() {
+    let fixture = Fixture::new();
+    fixture.write("docs/a.txt", "a");
+    fixture.commit("initial");
+
+    let output = fixture.run("", &["docs/a.txt", "--require-manifest"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No dependencies file found for docs/a.txt"),
+        "{stderr}"
+    );
+    // Without the option, the directory is monitored
+    assert!(fixture.run("", &["docs/a.txt"]).status.success());
+}
+
+#[test]
+fn a_manifest_without_an_entry_is_only_a_warning() {
+    let fixture = Fixture::new();
+    fixture.write("docs/.deps.toml", "[\"other.txt\"]\ndependencies = []\n");
+    fixture.write("docs/a.txt", "a");
+    fixture.commit("initial");
+
+    let output = fixture.run("", &["docs/a.txt", "--require-manifest"]);
+    assert!(output.status.success());
+    let output = fixture.run(
+        "",
+        &["docs/a.txt", "--require-manifest", "--fail-on-warning"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn globs_and_files_commands_need_no_manifest() {
+    let fixture = Fixture::new();
+    fixture.write("docs/a.txt", "a");
+    fixture.write("docs/b.txt", "b");
+    fixture.commit("initial");
+
+    assert!(fixture
+        .run("", &["docs/*.txt", "--require-manifest"])
+        .status
+        .success());
+    assert!(fixture
+        .run(
+            "",
+            &[
+                "docs/a.txt",
+                "--require-manifest",
+                "--files-command",
+                "echo b.txt"
+            ]
+        )
+        .status
+        .success());
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` committed, then changed in a second commit, and the tag `v1` on the first.
+/// Returns both hashes.
+fn fixture() -> (Fixture, String, String) {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "v1");
+    let first = fixture.commit("v1");
+    fixture.git(&["tag", "v1"]);
+    fixture.write("a.txt", "v2");
+    let second = fixture.commit("v2");
+    (fixture, first, second)
+}
+
+#[test]
+fn head_ahead_of_the_ref_is_a_warning() {
+    let (fixture, first, second) = fixture();
+
+    let output = fixture.run("", &["a.txt", "--ref", "v1"]);
+    assert_eq!(common::stdout(output.clone()), format!("{first}\n"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "The latest commit of a.txt is {first} at v1, but {second} at HEAD"
+        )),
+        "{stderr}"
+    );
+
+    let output = fixture.run("", &["a.txt", "--ref", "v1", "--fail-on-warning"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn the_same_commit_at_the_ref_is_fine() {
+    let (fixture, _, second) = fixture();
+    fixture.git(&["tag", "v2"]);
+
+    let output = fixture.run("", &["a.txt", "--ref", "v2", "--fail-on-warning"]);
+    assert_eq!(common::stdout(output), format!("{second}\n"));
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::Fixture;
+
+/// Two repositories with one committed file each, and the absolute path of the second file
+fn fixtures() -> (Fixture, Fixture, String) {
+    let first = Fixture::new();
+    first.write("a.txt", "a");
+    first.commit("initial");
+    let second = Fixture::new();
+    second.write("b.txt", "b");
+    second.commit("initial");
+    let other = second.path("b.txt").to_str().unwrap().to_string();
+    (first, second, other)
+}
+
+#[test]
+fn files_of_two_repositories_are_a_warning() {
+    let (first, _second, other) = fixtures();
+
+    let output = first.run("", &["a.txt", &other]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Monitored files span multiple repositories"),
+        "{stderr}"
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 2);
+}
+
+#[test]
+fn single_repo_makes_it_an_error() {
+    let (first, _second, other) = fixtures();
+
+    let output = first.run("", &["a.txt", &other, "--single-repo"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Monitored files span multiple repositories"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn files_of_one_repository_are_fine() {
+    let (first, _second, _) = fixtures();
+    first.write("c.txt", "c");
+    first.commit("second");
+
+    let output = first.run("", &["a.txt", "c.txt", "--single-repo"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("multiple repositories"), "{stderr}");
+}
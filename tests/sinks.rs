@@ -0,0 +1,109 @@
+mod common;
+
+use std::{
+    fs::{self, File},
+    os::unix::fs::MetadataExt,
+    time::{Duration, SystemTime},
+};
+
+use common::Fixture;
+
+/// A fixture with one committed file, and `stamp` whose modification time is an hour ago
+fn fixture() -> (Fixture, SystemTime) {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture.write("stamp", "content");
+    let old = SystemTime::now() - Duration::from_secs(3600);
+    File::options()
+        .write(true)
+        .open(fixture.path("stamp"))
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+    (fixture, old)
+}
+
+#[test]
+fn a_failing_sink_leaves_the_others_unchanged() {
+    let (fixture, old) = fixture();
+    fixture.write("out.txt", "old");
+
+    let output = fixture.run(
+        "",
+        &[
+            "a.txt",
+            "--output",
+            "out.txt",
+            "--touch",
+            "stamp",
+            "--json-out",
+            "missing/out.json",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(fs::read_to_string(fixture.path("out.txt")).unwrap(), "old");
+    let modified = fs::metadata(fixture.path("stamp"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert_eq!(modified, old);
+    let leftovers: Vec<_> = fs::read_dir(&fixture.root)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+}
+
+#[test]
+fn touch_keeps_the_file_and_its_hard_links() {
+    let (fixture, old) = fixture();
+    fs::hard_link(fixture.path("stamp"), fixture.path("link")).unwrap();
+    let before = fs::metadata(fixture.path("stamp")).unwrap();
+
+    fixture.stdout("", &["a.txt", "--touch", "stamp"]);
+    let after = fs::metadata(fixture.path("stamp")).unwrap();
+    assert_eq!(after.ino(), before.ino());
+    assert_eq!(after.nlink(), 2);
+    assert!(after.modified().unwrap() > old);
+    assert_eq!(fs::read_to_string(fixture.path("link")).unwrap(), "content");
+}
+
+#[test]
+fn touch_follows_a_symbolic_link() {
+    let (fixture, old) = fixture();
+    std::os::unix::fs::symlink("stamp", fixture.path("link")).unwrap();
+
+    fixture.stdout("", &["a.txt", "--touch", "link"]);
+    let link = fs::symlink_metadata(fixture.path("link")).unwrap();
+    assert!(link.file_type().is_symlink());
+    let target = fs::metadata(fixture.path("stamp")).unwrap();
+    assert!(target.modified().unwrap() > old);
+    assert_eq!(
+        fs::read_to_string(fixture.path("stamp")).unwrap(),
+        "content"
+    );
+}
+
+#[test]
+fn touch_creates_a_missing_file() {
+    let (fixture, _) = fixture();
+
+    fixture.stdout("", &["a.txt", "--touch", "new"]);
+    assert_eq!(fs::read(fixture.path("new")).unwrap(), b"");
+}
+
+#[test]
+fn two_sinks_cannot_write_the_same_file() {
+    let (fixture, _) = fixture();
+    fixture.write("out.txt", "old");
+
+    let output = fixture.run(
+        "",
+        &["a.txt", "--output", "out.txt", "--json-out", "./out.txt"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("write the same file"));
+    assert_eq!(fs::read_to_string(fixture.path("out.txt")).unwrap(), "old");
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::Fixture;
+
+fn run_with_epoch(fixture: &Fixture, epoch: &str) -> std::process::Output {
+    fixture.run_with(
+        "",
+        &["a.txt", "--date", "--respect-source-date-epoch"],
+        |command| {
+            command.env("SOURCE_DATE_EPOCH", epoch);
+        },
+    )
+}
+
+#[test]
+fn the_epoch_replaces_the_date_of_a_clean_tree() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    // 2000-02-29 12:00 UTC
+    assert_eq!(
+        common::stdout(run_with_epoch(&fixture, "951825600")),
+        "2000-02-29\n"
+    );
+    // Without the option, the variable is ignored
+    let output = fixture.run_with("", &["a.txt", "--date"], |command| {
+        command.env("SOURCE_DATE_EPOCH", "951825600");
+    });
+    assert_ne!(common::stdout(output), "2000-02-29\n");
+}
+
+#[test]
+fn the_commit_date_is_used_for_a_dirty_tree() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture.write("a.txt", "changed");
+
+    let commit_date = fixture.git(&["log", "-1", "--format=%cs"]);
+    assert_eq!(
+        common::stdout(run_with_epoch(&fixture, "951825600")).trim_end(),
+        commit_date
+    );
+}
+
+#[test]
+fn an_invalid_epoch_is_an_error() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    let output = run_with_epoch(&fixture, "yesterday");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Invalid SOURCE_DATE_EPOCH: yesterday"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    assert!(output.stdout.is_empty());
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` and `b.txt` as entries of their own, with `dep.txt` a dependency of `b.txt`
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = [\"dep.txt\"]\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    fixture.write("dep.txt", "dep");
+    let hash = fixture.commit("initial");
+    (fixture, hash)
+}
+
+#[test]
+fn only_staged_changes_count() {
+    let (fixture, hash) = fixture();
+    fixture.write("a.txt", "staged");
+    fixture.git(&["add", "a.txt"]);
+    fixture.write("b.txt", "unstaged");
+    fixture.write("untracked.txt", "untracked");
+
+    let output = fixture.stdout("", &["a.txt", "b.txt", "--staged-only"]);
+    assert_eq!(output, format!("a.txt\t{hash} DIRTY\nb.txt\t{hash}\n"));
+    // Without the option, both are dirty
+    let output = fixture.stdout("", &["a.txt", "b.txt"]);
+    assert_eq!(
+        output,
+        format!("a.txt\t{hash} DIRTY\nb.txt\t{hash} DIRTY\n")
+    );
+}
+
+#[test]
+fn a_staged_dependency_counts() {
+    let (fixture, hash) = fixture();
+    fixture.write("dep.txt", "staged");
+    fixture.git(&["add", "dep.txt"]);
+
+    let output = fixture.stdout("", &["b.txt", "--staged-only"]);
+    assert_eq!(output, format!("{hash} DIRTY\n"));
+    let output = fixture.stdout("", &["a.txt", "--staged-only"]);
+    assert_eq!(output, format!("{hash}\n"));
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::Fixture;
+
+/// Every line is `KEY VALUE` with a key of uppercase letters, digits and `_`
+fn assert_well_formed(output: &str) {
+    for line in output.lines() {
+        let (key, value) = line.split_once(' ').unwrap();
+        assert!(
+            key.chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'),
+            "{line}"
+        );
+        assert!(!value.contains('\n'), "{line}");
+    }
+}
+
+#[test]
+fn stamp_lines_are_well_formed() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    let hash = fixture.commit("initial");
+    let short = fixture.git(&["rev-parse", "--short", "HEAD"]);
+    let date = fixture.git(&["log", "-1", "--format=%cs"]);
+    fixture.write("a.txt", "uncommitted");
+
+    let output = fixture.stdout("", &["a.txt", "--format", "stamp"]);
+    assert_well_formed(&output);
+    assert_eq!(
+        output,
+        format!(
+            "STABLE_CHANGE_MONITOR_COMMIT {hash}\nSTABLE_CHANGE_MONITOR_SHORT {short}\n\
+             STABLE_CHANGE_MONITOR_DATE {date}\nSTABLE_CHANGE_MONITOR_DIRTY 1\n"
+        )
+    );
+}
+
+#[test]
+fn the_age_is_volatile_and_files_are_part_of_the_key() {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "a");
+    fixture.write("docs/.deps.toml", "[\"main.typ\"]\ndependencies = []\n");
+    fixture.write("docs/main.typ", "main");
+    fixture.commit("initial");
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "docs/main.typ",
+            "--format",
+            "stamp",
+            "--age-seconds",
+        ],
+    );
+    assert_well_formed(&output);
+    assert!(
+        output.contains("\nCHANGE_MONITOR_A_TXT_AGE_SECONDS "),
+        "{output}"
+    );
+    assert!(
+        output.contains("\nSTABLE_CHANGE_MONITOR_DOCS_MAIN_TYP_COMMIT "),
+        "{output}"
+    );
+    assert!(
+        !output.contains("STABLE_CHANGE_MONITOR_A_TXT_AGE_SECONDS"),
+        "{output}"
+    );
+}
+
+#[test]
+fn the_formatted_age_is_volatile() {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "--format",
+            "stamp",
+            "--age-seconds",
+            "--age-format",
+            "days",
+        ],
+    );
+    assert!(output.contains("\nCHANGE_MONITOR_AGE 0 days\n"), "{output}");
+    assert!(!output.contains("STABLE_CHANGE_MONITOR_AGE"), "{output}");
+}
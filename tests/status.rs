@@ -0,0 +1,30 @@
+mod common;
+
+use common::Fixture;
+use std::{ffi::OsStr, fs, os::unix::ffi::OsStrExt};
+
+#[test]
+fn non_utf8_paths_are_dirty() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fs::write(fixture.root.join(OsStr::from_bytes(b"caf\xe9.txt")), "b").unwrap();
+
+    let output = fixture.stdout("", &["."]);
+    assert!(output.trim_end().ends_with(" DIRTY"), "{output}");
+}
+
+#[test]
+fn paths_with_spaces_and_quotes_are_listed_unquoted() {
+    let fixture = Fixture::new();
+    fixture.write("sp ace.txt", "a");
+    fixture.write("q\"uote.txt", "b");
+    fixture.commit("initial");
+    fixture.write("sp ace.txt", "changed");
+    fixture.write("q\"uote.txt", "changed");
+
+    for format in ["v1", "v2"] {
+        let output = fixture.stdout("", &[".", "--dirty-only", "--status-format", format]);
+        assert_eq!(output, "DIRTY\nq\"uote.txt\nsp ace.txt\n", "{format}");
+    }
+}
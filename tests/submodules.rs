@@ -0,0 +1,46 @@
+mod common;
+
+use common::Fixture;
+
+/// A superproject with `a.txt` and the submodule `sub`, and the repository of the submodule
+fn fixtures() -> (Fixture, Fixture) {
+    let library = Fixture::new();
+    library.write("lib.txt", "v1");
+    library.commit("v1");
+
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.git(&[
+        "-c",
+        "protocol.file.allow=always",
+        "submodule",
+        "add",
+        "--quiet",
+        library.root.to_str().unwrap(),
+        "sub",
+    ]);
+    fixture.commit("initial");
+    (fixture, library)
+}
+
+/// Commits a change in the submodule and records the new commit in the superproject
+fn bump(fixture: &Fixture) {
+    fixture.write("sub/lib.txt", "v2");
+    fixture.git(&["-C", "sub", "commit", "--quiet", "--all", "--message", "v2"]);
+    fixture.git(&["add", "sub"]);
+    fixture.commit("bump the submodule");
+}
+
+#[test]
+fn bumping_a_submodule_changes_the_fingerprint_with_its_pointer() {
+    let (fixture, _library) = fixtures();
+    let args = ["a.txt", "--fingerprint", "--include-submodule-pointers"];
+    let before = fixture.stdout("", &args);
+    let without = fixture.stdout("", &["a.txt", "--fingerprint"]);
+    assert_ne!(before, without);
+
+    bump(&fixture);
+    assert_ne!(fixture.stdout("", &args), before);
+    // Without the option, the submodule is skipped
+    assert_eq!(fixture.stdout("", &["a.txt", "--fingerprint"]), without);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn a_multi_line_template_is_applied_per_file() {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    let hash = fixture.commit("initial");
+    let short = fixture.git(&["rev-parse", "--short", "HEAD"]);
+    let date = fixture.git(&["log", "-1", "--format=%cs"]);
+    fixture.write("b.txt", "changed");
+    fixture.write(
+        "banner.txt",
+        "{{{file}}}\ncommit {commit} ({short})\ndate {date} [{dirty}]\n",
+    );
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "b.txt",
+            "--template",
+            "banner.txt",
+            "--clean-marker",
+            "clean",
+        ],
+    );
+    assert_eq!(
+        output,
+        format!(
+            "{{a.txt}}\ncommit {hash} ({short})\ndate {date} [clean]\n\
+             {{b.txt}}\ncommit {hash} ({short})\ndate {date} [DIRTY]\n"
+        )
+    );
+}
+
+#[test]
+fn an_invalid_template_is_an_error() {
+    let fixture = Fixture::new();
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+
+    for (template, message) in [
+        ("{commit", "Unclosed placeholder in template: {commit"),
+        ("{hash}", "Unknown template placeholder: {hash}"),
+        ("a } b", "Unmatched } in template"),
+    ] {
+        fixture.write("banner.txt", template);
+        let output = fixture.run("", &["a.txt", "--template", "banner.txt"]);
+        assert_eq!(output.status.code(), Some(1), "{template}");
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{stderr}");
+        assert!(!stderr.contains("panicked"), "{stderr}");
+    }
+
+    let output = fixture.run("", &["a.txt", "--template", "missing.txt"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Failed to read template missing.txt"),
+        "{stderr}"
+    );
+}
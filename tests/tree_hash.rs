@@ -0,0 +1,92 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn the_tree_hash_of_a_directory_is_stable_across_a_no_op_commit() {
+    let fixture = Fixture::new();
+    fixture.write("docs/a.txt", "a");
+    fixture.write("other.txt", "other");
+    fixture.commit("initial");
+    let tree = fixture.git(&["rev-parse", "HEAD:docs"]);
+
+    // Without an entry, the directory is monitored
+    let output = fixture.stdout("", &["docs/a.txt", "--tree-hash"]);
+    assert_eq!(output, format!("{tree}\n"));
+
+    fixture.git(&["commit", "--quiet", "--allow-empty", "--message", "no-op"]);
+    fixture.write("other.txt", "changed");
+    fixture.commit("outside of the directory");
+    assert_eq!(fixture.stdout("", &["docs/a.txt", "--tree-hash"]), output);
+
+    fixture.write("docs/a.txt", "changed");
+    fixture.commit("change");
+    assert_ne!(fixture.stdout("", &["docs/a.txt", "--tree-hash"]), output);
+}
+
+#[test]
+fn the_tree_hash_of_files_survives_a_revert() {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = [\"dep.txt\"]\n");
+    fixture.write("a.txt", "a");
+    fixture.write("dep.txt", "dep");
+    fixture.commit("initial");
+    let before = fixture.stdout("", &["a.txt", "--tree-hash"]);
+    let commit_before = fixture.stdout("", &["a.txt"]);
+
+    fixture.write("dep.txt", "changed");
+    fixture.commit("change");
+    let changed = fixture.stdout("", &["a.txt", "--tree-hash"]);
+    assert_ne!(changed, before);
+
+    fixture.git(&["revert", "--no-edit", "HEAD"]);
+    assert_eq!(fixture.stdout("", &["a.txt", "--tree-hash"]), before);
+    // Unlike the commit hash
+    assert_ne!(fixture.stdout("", &["a.txt"]), commit_before);
+}
+
+#[test]
+fn uncommitted_changes_are_marked() {
+    let fixture = Fixture::new();
+    fixture.write("docs/a.txt", "a");
+    fixture.commit("initial");
+    let tree = fixture.git(&["rev-parse", "HEAD:docs"]);
+    fixture.write("docs/a.txt", "uncommitted");
+
+    let output = fixture.stdout("", &["docs/a.txt", "--tree-hash"]);
+    assert_eq!(output, format!("{tree} DIRTY\n"));
+}
+
+#[test]
+fn paths_with_quotes_are_hashed() {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = ['q/we\"ird.txt']\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("q/we\"ird.txt", "weird");
+    fixture.commit("initial");
+    let before = fixture.stdout("", &["a.txt", "--tree-hash"]);
+
+    fixture.write("q/we\"ird.txt", "changed");
+    fixture.commit("change");
+    assert_ne!(fixture.stdout("", &["a.txt", "--tree-hash"]), before);
+}
+
+#[test]
+fn no_tracked_files_is_an_error() {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture.git(&["rm", "--quiet", "--cached", "a.txt"]);
+
+    let output = fixture.run("", &["a.txt", "--tree-hash"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Failed to compute the tree hash of a.txt"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn every_row_has_the_columns_of_the_header() {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = []\ndirty = false\n\n\
+         [\"c.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.write("b.txt", "b");
+    let hash = fixture.commit("initial");
+    let short = fixture.git(&["rev-parse", "--short", "HEAD"]);
+    let date = fixture.git(&["log", "-1", "--format=%cs"]);
+    fixture.write("a.txt", "uncommitted");
+    fixture.write("b.txt", "uncommitted");
+    // Without commits
+    fixture.write("c.txt", "c");
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "a.txt",
+            "b.txt",
+            "c.txt",
+            "--format",
+            "tsv",
+            "--empty-value",
+            "NONE",
+        ],
+    );
+    assert_eq!(
+        output,
+        format!(
+            "file\tcommit\tshort\tdate\tdirty\n\
+             a.txt\t{hash}\t{short}\t{date}\t1\n\
+             b.txt\t{hash}\t{short}\t{date}\t\n\
+             c.txt\tNONE\tNONE\t\t\n"
+        )
+    );
+    // Also if the first row has no dirty state
+    let output = fixture.stdout("", &["b.txt", "a.txt", "--format", "tsv"]);
+    assert_eq!(
+        output,
+        format!(
+            "file\tcommit\tshort\tdate\tdirty\n\
+             b.txt\t{hash}\t{short}\t{date}\t\n\
+             a.txt\t{hash}\t{short}\t{date}\t1\n"
+        )
+    );
+}
+
+#[test]
+fn the_optional_columns_are_empty_for_a_placeholder() {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"c.txt\"]\ndependencies = []\n",
+    );
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture.write("c.txt", "c");
+
+    let output = fixture.stdout(
+        "",
+        &[
+            "c.txt",
+            "a.txt",
+            "--format",
+            "tsv",
+            "--empty-value",
+            "NONE",
+            "--with-branch",
+            "--with-diffstat",
+            "--no-dirty-check",
+        ],
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines[0],
+        "file\tcommit\tshort\tdate\tfiles_changed\tinsertions\tdeletions\tbranch"
+    );
+    assert_eq!(lines[1], "c.txt\tNONE\tNONE\t\t\t\t\t");
+    assert!(lines[2].ends_with("\t1\t1\t0\tmain"), "{}", lines[2]);
+}
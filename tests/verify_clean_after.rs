@@ -0,0 +1,82 @@
+mod common;
+
+use std::{fs, os::unix::fs::PermissionsExt};
+
+use common::Fixture;
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "a");
+    fixture.commit("initial");
+    fixture
+}
+
+#[test]
+fn an_unchanged_tree_passes() {
+    let fixture = fixture();
+    let head = fixture.git(&["rev-parse", "HEAD"]);
+
+    let output = fixture.stdout("", &["a.txt", "--verify-clean-after"]);
+    assert_eq!(output, format!("{head}\n"));
+}
+
+#[test]
+fn a_modification_during_the_run_fails() {
+    let fixture = fixture();
+    // A git wrapper that modifies the file after the first dirty check, like a concurrent build step
+    let bin = fixture.path(".git/racing-bin");
+    fs::create_dir_all(&bin).unwrap();
+    let git = std::process::Command::new("sh")
+        .args(["-c", "command -v git"])
+        .output()
+        .unwrap();
+    let git = String::from_utf8_lossy(&git.stdout).trim().to_string();
+    let marker = fixture.path(".git/raced");
+    let file = fixture.path("a.txt");
+    fs::write(
+        bin.join("git"),
+        format!(
+            "#!/bin/sh\n'{git}' \"$@\"\nstatus=$?\n\
+             case \" $* \" in *' status '*) [ -e '{marker}' ] || {{ touch '{marker}'; echo changed > '{file}'; }};; esac\n\
+             exit $status\n",
+            marker = marker.display(),
+            file = file.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(bin.join("git"), fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let output = fixture.run_with("", &["a.txt", "--verify-clean-after"], |command| {
+        command.env("PATH", &path);
+    });
+    assert!(marker.exists());
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ERROR"), "{stderr}");
+
+    // Without the re-check, the outdated result of the first queries is printed
+    fs::remove_file(&marker).unwrap();
+    fixture.git(&["checkout", "--", "a.txt"]);
+    let head = fixture.git(&["rev-parse", "HEAD"]);
+    let output = fixture.run_with("", &["a.txt"], |command| {
+        command.env("PATH", &path);
+    });
+    assert!(marker.exists());
+    assert_eq!(common::stdout(output), format!("{head}\n"));
+}
+
+#[test]
+fn the_dirty_check_cannot_be_skipped() {
+    let fixture = fixture();
+
+    let output = fixture.run("", &["a.txt", "--verify-clean-after", "--no-dirty-check"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be combined"));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::Fixture;
+
+/// `a.txt` is changed in four commits with known author dates, `b.txt` in one.
+/// Returns the hashes of the commits, oldest first.
+fn fixture() -> (Fixture, Vec<String>) {
+    let fixture = Fixture::new();
+    fixture.write(
+        ".deps.toml",
+        "[\"a.txt\"]\ndependencies = []\n\n[\"b.txt\"]\ndependencies = []\n",
+    );
+    let mut commits = Vec::new();
+    for day in 1..=4 {
+        fixture.write("a.txt", day.to_string());
+        if day == 2 {
+            fixture.write("b.txt", "b");
+        }
+        fixture.git(&["add", "--all"]);
+        let date = format!("2024-07-0{day}T12:00:00+00:00");
+        fixture.git(&["commit", "--quiet", "--message", "change", "--date", &date]);
+        commits.push(fixture.git(&["rev-parse", "HEAD"]));
+    }
+    fixture.write("other.txt", "unrelated");
+    fixture.commit("unrelated");
+    (fixture, commits)
+}
+
+#[test]
+fn exactly_n_commits_are_listed_newest_first() {
+    let (fixture, commits) = fixture();
+
+    let output = fixture.stdout("", &["a.txt", "--window", "3", "--identity", "author"]);
+    assert_eq!(
+        output,
+        format!(
+            "{} 2024-07-04\n{} 2024-07-03\n{} 2024-07-02\n",
+            commits[3], commits[2], commits[1]
+        )
+    );
+
+    let output = fixture.stdout("", &["a.txt", "--window", "3", "--format", "json"]);
+    assert_eq!(output.lines().count(), 3, "{output}");
+    assert!(output
+        .lines()
+        .all(|line| line.starts_with("{ \"file\": \"a.txt\"")));
+}
+
+#[test]
+fn a_file_with_fewer_commits_gets_fewer_lines() {
+    let (fixture, commits) = fixture();
+
+    let output = fixture.stdout(
+        "",
+        &["a.txt", "b.txt", "--window", "2", "--identity", "author"],
+    );
+    assert_eq!(
+        output,
+        format!(
+            "a.txt\t{} 2024-07-04\na.txt\t{} 2024-07-03\nb.txt\t{} 2024-07-02\n",
+            commits[3], commits[2], commits[1]
+        )
+    );
+}
+
+#[test]
+fn other_formats_are_not_supported() {
+    let (fixture, _) = fixture();
+
+    let output = fixture.run("", &["a.txt", "--window", "2", "--format", "shell"]);
+    assert_eq!(output.status.code(), Some(1));
+}
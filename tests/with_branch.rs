@@ -0,0 +1,45 @@
+mod common;
+
+use common::Fixture;
+
+fn fixture() -> (Fixture, String) {
+    let fixture = Fixture::new();
+    fixture.write(".deps.toml", "[\"a.txt\"]\ndependencies = []\n");
+    fixture.write("a.txt", "a");
+    let head = fixture.commit("initial");
+    (fixture, head)
+}
+
+#[test]
+fn the_named_branch_is_reported() {
+    let (fixture, head) = fixture();
+    fixture.git(&["checkout", "--quiet", "-b", "feature/x"]);
+
+    let output = fixture.stdout("", &["a.txt", "--with-branch"]);
+    assert_eq!(output, format!("{head} feature/x\n"));
+    let output = fixture.stdout("", &["a.txt", "--with-branch", "--format", "json"]);
+    assert!(output.contains("\"branch\": \"feature/x\""), "{output}");
+}
+
+#[test]
+fn a_detached_head_is_reported_as_head_or_the_placeholder() {
+    let (fixture, head) = fixture();
+    fixture.git(&["checkout", "--quiet", "--detach"]);
+
+    let output = fixture.stdout("", &["a.txt", "--with-branch"]);
+    assert_eq!(output, format!("{head} HEAD\n"));
+    let output = fixture.stdout(
+        "",
+        &["a.txt", "--with-branch", "--detached-branch", "detached"],
+    );
+    assert_eq!(output, format!("{head} detached\n"));
+}
+
+#[test]
+fn the_placeholder_requires_the_branch() {
+    let (fixture, _) = fixture();
+
+    let output = fixture.run("", &["a.txt", "--detached-branch", "detached"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("requires --with-branch"));
+}